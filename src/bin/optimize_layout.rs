@@ -0,0 +1,413 @@
+//! Suggests an input table that takes less effort to type the text of a corpus.
+//!
+//! usage: optimize_layout <corpus.txt> [--base <table.json>] [--name <plane>] [--starts-ime]
+//! [--output <table.json>]
+//!
+//! Typing a character flicks both sticks to its cell. A stick staying in its sector costs
+//! nothing, sliding along the edge costs by the sectors passed, and diagonal sectors are
+//! a bit harder to hit than straight ones. Each later character of a cell costs one more
+//! step of the multi-tap. The contents of cells are swapped while the total cost over the
+//! characters and the pairs of characters in the corpus decreases.
+//!
+//! With a base table, like the ones the app writes to `tables/builtin` in appdata, characters
+//! sharing a cell stay together and only the multi-tap order follows the corpus. Without one,
+//! each character of the corpus gets its own cell while there are free cells, and the rarest
+//! ones are left out once the cells are full.
+//! The output is a table file the app loads from `tables/<plane>.json` in appdata.
+
+#[path = "../table_file.rs"]
+mod table_file;
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::process::exit;
+use table_file::{ACTION_PREFIX, CELLS, RESERVED_CELLS, TableFile};
+
+const STRAIGHT_FLICK: f64 = 1.0;
+const DIAGONAL_FLICK: f64 = 1.2;
+const SLIDE_PER_SECTOR: f64 = 0.4;
+const TAP_STEP: f64 = 1.0;
+// the entries of a cell filled from the corpus; more are hard to choose with the multi-tap
+const MAX_TAPS: usize = 4;
+
+struct Args {
+    corpus: String,
+    base: Option<String>,
+    name: Option<String>,
+    starts_ime: bool,
+    output: Option<String>,
+}
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: optimize_layout <corpus.txt> [--base <table.json>] [--name <plane>] \
+         [--starts-ime] [--output <table.json>]"
+    );
+    exit(1)
+}
+
+fn parse_args() -> Args {
+    let mut corpus = None;
+    let mut args = Args {
+        corpus: String::new(),
+        base: None,
+        name: None,
+        starts_ime: false,
+        output: None,
+    };
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--base" => args.base = Some(iter.next().unwrap_or_else(|| usage())),
+            "--name" => args.name = Some(iter.next().unwrap_or_else(|| usage())),
+            "--output" => args.output = Some(iter.next().unwrap_or_else(|| usage())),
+            "--starts-ime" => args.starts_ime = true,
+            _ if arg.starts_with("--") || corpus.is_some() => usage(),
+            _ => corpus = Some(arg),
+        }
+    }
+    args.corpus = corpus.unwrap_or_else(|| usage());
+    args
+}
+
+fn flick(sector: usize) -> f64 {
+    if sector.is_multiple_of(2) {
+        STRAIGHT_FLICK
+    } else {
+        DIAGONAL_FLICK
+    }
+}
+
+fn stick_move(from: Option<usize>, to: usize) -> f64 {
+    match from {
+        Some(from) if from == to => 0.0,
+        Some(from) => {
+            let steps = (from + 8 - to) % 8;
+            let steps = steps.min(8 - steps);
+            (steps as f64 * SLIDE_PER_SECTOR).min(flick(to))
+        }
+        None => flick(to),
+    }
+}
+
+// moving both sticks from the cell, or from the rest, to the cell
+fn move_cost(from: Option<usize>, to: usize) -> f64 {
+    stick_move(from.map(|x| x / 8), to / 8) + stick_move(from.map(|x| x % 8), to % 8)
+}
+
+#[derive(Default)]
+struct Counts {
+    chars: HashMap<char, f64>,
+    // the first character of words
+    starts: HashMap<char, f64>,
+    pairs: HashMap<(char, char), f64>,
+    total: f64,
+}
+
+fn count_corpus(text: &str) -> Counts {
+    let mut counts = Counts::default();
+    let mut prev = None;
+    for c in text.chars() {
+        if c.is_whitespace() || c.is_control() {
+            prev = None;
+            continue;
+        }
+        *counts.chars.entry(c).or_default() += 1.0;
+        match prev {
+            None => *counts.starts.entry(c).or_default() += 1.0,
+            Some(prev) => *counts.pairs.entry((prev, c)).or_default() += 1.0,
+        }
+        counts.total += 1.0;
+        prev = Some(c);
+    }
+    counts
+}
+
+fn entry_char(entry: &str) -> Option<char> {
+    let mut chars = entry.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c != ACTION_PREFIX || entry.len() == 1 => Some(c),
+        _ => None,
+    }
+}
+
+// the entries of a cell moved together
+type Unit = Vec<String>;
+
+struct Problem {
+    units: Vec<Unit>,
+    cells: Vec<usize>,
+    starts: Vec<f64>,
+    // pairs[from * n + to]
+    pairs: Vec<f64>,
+}
+
+impl Problem {
+    fn new(units: Vec<Unit>, cells: Vec<usize>, counts: &Counts) -> Self {
+        let n = units.len();
+        let mut unit_of = HashMap::new();
+        for (idx, unit) in units.iter().enumerate() {
+            for c in unit.iter().filter_map(|x| entry_char(x)) {
+                unit_of.entry(c).or_insert(idx);
+            }
+        }
+        let mut starts = vec![0.0; n];
+        for (c, count) in &counts.starts {
+            if let Some(&u) = unit_of.get(c) {
+                starts[u] += count;
+            }
+        }
+        let mut pairs = vec![0.0; n * n];
+        for ((a, b), count) in &counts.pairs {
+            if let (Some(&u), Some(&v)) = (unit_of.get(a), unit_of.get(b)) {
+                pairs[u * n + v] += count;
+            }
+        }
+        Self {
+            units,
+            cells,
+            starts,
+            pairs,
+        }
+    }
+
+    fn tap_cost(&self, counts: &Counts) -> f64 {
+        let mut cost = 0.0;
+        for unit in &self.units {
+            for (i, entry) in unit.iter().enumerate() {
+                let count = entry_char(entry)
+                    .and_then(|c| counts.chars.get(&c))
+                    .copied()
+                    .unwrap_or_default();
+                cost += count * i as f64 * TAP_STEP;
+            }
+        }
+        cost
+    }
+
+    fn move_cost(&self) -> f64 {
+        let n = self.units.len();
+        let mut cost = 0.0;
+        for u in 0..n {
+            cost += self.starts[u] * move_cost(None, self.cells[u]);
+            for v in 0..n {
+                cost += self.pairs[u * n + v] * move_cost(Some(self.cells[u]), self.cells[v]);
+            }
+        }
+        cost
+    }
+
+    // the part of the move cost involving either of the units
+    fn touching_cost(&self, a: usize, b: usize) -> f64 {
+        let n = self.units.len();
+        let mut cost = 0.0;
+        for u in [a, b] {
+            cost += self.starts[u] * move_cost(None, self.cells[u]);
+            for v in 0..n {
+                cost += self.pairs[u * n + v] * move_cost(Some(self.cells[u]), self.cells[v]);
+                if v != a && v != b {
+                    cost += self.pairs[v * n + u] * move_cost(Some(self.cells[v]), self.cells[u]);
+                }
+            }
+        }
+        cost
+    }
+
+    // swaps cells while it gets better
+    fn optimize(&mut self) {
+        let n = self.units.len();
+        loop {
+            let mut improved = false;
+            for a in 0..n {
+                for b in a + 1..n {
+                    let before = self.touching_cost(a, b);
+                    self.cells.swap(a, b);
+                    if self.touching_cost(a, b) + 1e-9 < before {
+                        improved = true;
+                    } else {
+                        self.cells.swap(a, b);
+                    }
+                }
+            }
+            if !improved {
+                break;
+            }
+        }
+    }
+
+    fn to_table(&self) -> Vec<Vec<String>> {
+        let mut table = vec![vec![]; CELLS];
+        for (unit, &cell) in self.units.iter().zip(&self.cells) {
+            table[cell] = unit.clone();
+        }
+        table
+    }
+}
+
+fn free_cells() -> Vec<usize> {
+    let mut cells = (0..CELLS)
+        .filter(|x| !RESERVED_CELLS.contains(x))
+        .collect::<Vec<_>>();
+    // easier cells first
+    cells.sort_by(|&a, &b| move_cost(None, a).total_cmp(&move_cost(None, b)));
+    cells
+}
+
+fn frequency(counts: &Counts, entry: &str) -> f64 {
+    entry_char(entry)
+        .and_then(|c| counts.chars.get(&c))
+        .copied()
+        .unwrap_or_default()
+}
+
+// each character in its own cell, more frequent ones in easier cells;
+// returns the characters left out, the rarest ones
+fn units_from_corpus(counts: &Counts, cells: &[usize]) -> (Vec<Unit>, Vec<usize>, Vec<char>) {
+    let mut chars = counts.chars.iter().collect::<Vec<_>>();
+    chars.sort_by(|a, b| b.1.total_cmp(a.1).then(a.0.cmp(b.0)));
+    let mut units = vec![Unit::new(); cells.len()];
+    let mut dropped = vec![];
+    for (i, (&c, _)) in chars.into_iter().enumerate() {
+        // characters beyond the cells are later ones of the multi-tap
+        if i < cells.len() * MAX_TAPS {
+            units[i % cells.len()].push(c.to_string());
+        } else {
+            dropped.push(c);
+        }
+    }
+    (units, cells.to_vec(), dropped)
+}
+
+// the cells of the base where they are, empty ones included to move cells into
+fn units_from_base(base: &TableFile, cells: &[usize]) -> (Vec<Unit>, Vec<usize>) {
+    let units = cells
+        .iter()
+        .map(|&cell| base.table.get(cell).cloned().unwrap_or_default())
+        .collect();
+    (units, cells.to_vec())
+}
+
+fn main() {
+    let args = parse_args();
+    let text = std::fs::read_to_string(&args.corpus).unwrap_or_else(|err| {
+        eprintln!("reading {}: {err}", args.corpus);
+        exit(1)
+    });
+    let counts = count_corpus(&text);
+    if counts.total == 0.0 {
+        eprintln!("no characters in {}", args.corpus);
+        exit(1)
+    }
+
+    let base = args.base.as_ref().map(|path| {
+        File::open(path)
+            .map_err(|err| err.to_string())
+            .and_then(|file| {
+                serde_json::from_reader::<_, TableFile>(file).map_err(|err| err.to_string())
+            })
+            .unwrap_or_else(|err| {
+                eprintln!("reading {path}: {err}");
+                exit(1)
+            })
+    });
+
+    let cells = free_cells();
+    let (units, placed) = match &base {
+        Some(base) => units_from_base(base, &cells),
+        None => {
+            let (units, placed, dropped) = units_from_corpus(&counts, &cells);
+            if !dropped.is_empty() {
+                let dropped = dropped.into_iter().collect::<String>();
+                eprintln!(
+                    "no cell is left for {} characters: {dropped}",
+                    dropped.chars().count()
+                );
+            }
+            (units, placed)
+        }
+    };
+    let mut problem = Problem::new(units, placed, &counts);
+    let before = problem.move_cost() + problem.tap_cost(&counts);
+
+    // the more frequent characters first in the multi-tap
+    for unit in &mut problem.units {
+        unit.sort_by(|a, b| frequency(&counts, b).total_cmp(&frequency(&counts, a)));
+    }
+    problem.optimize();
+    let after = problem.move_cost() + problem.tap_cost(&counts);
+    eprintln!(
+        "cost per character: {:.3} -> {:.3}",
+        before / counts.total,
+        after / counts.total
+    );
+
+    let file = TableFile {
+        name: args
+            .name
+            .or_else(|| base.as_ref().map(|x| x.name.clone()))
+            .unwrap_or_else(|| "english".to_owned()),
+        starts_ime: args.starts_ime || base.as_ref().is_some_and(|x| x.starts_ime),
+        table: problem.to_table(),
+    };
+    let result = match &args.output {
+        Some(path) => File::create(path)
+            .map_err(serde_json::Error::io)
+            .and_then(|writing| serde_json::to_writer_pretty(writing, &file)),
+        None => serde_json::to_writer_pretty(std::io::stdout(), &file),
+    };
+    if let Err(err) = result {
+        eprintln!("writing table: {err}");
+        exit(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stick_moves() {
+        // staying costs nothing
+        assert_eq!(stick_move(Some(3), 3), 0.0);
+        // from the rest, a flick to the sector
+        assert_eq!(stick_move(None, 0), STRAIGHT_FLICK);
+        assert_eq!(stick_move(None, 1), DIAGONAL_FLICK);
+        // sliding along the edge, either way round
+        assert_eq!(stick_move(Some(0), 1), SLIDE_PER_SECTOR);
+        assert_eq!(stick_move(Some(1), 7), 2.0 * SLIDE_PER_SECTOR);
+        // no more than flicking again
+        assert_eq!(stick_move(Some(0), 4), STRAIGHT_FLICK);
+    }
+
+    #[test]
+    fn both_sticks_moved() {
+        assert_eq!(move_cost(None, 0), 2.0 * STRAIGHT_FLICK);
+        assert_eq!(move_cost(None, 8 + 1), 2.0 * DIAGONAL_FLICK);
+        assert_eq!(move_cost(Some(2 * 8 + 2), 2 * 8 + 3), SLIDE_PER_SECTOR);
+        assert_eq!(move_cost(Some(5), 5), 0.0);
+    }
+
+    #[test]
+    fn optimized_cheaper() {
+        let counts = count_corpus("the then there these those other either");
+        // the frequent characters start in the hardest cells
+        let mut cells = free_cells();
+        cells.reverse();
+        let (units, placed, dropped) = units_from_corpus(&counts, &cells);
+        assert!(dropped.is_empty());
+        let mut problem = Problem::new(units, placed, &counts);
+        let before = problem.move_cost();
+        problem.optimize();
+        assert!(problem.move_cost() < before);
+    }
+
+    #[test]
+    fn rarest_left_out() {
+        let cells = [0, 1];
+        let counts = count_corpus("aaaaaa bbbbb cccc ddd ee f g h i");
+        let (units, _, dropped) = units_from_corpus(&counts, &cells);
+        assert!(units.iter().all(|x| x.len() == MAX_TAPS));
+        assert_eq!(units[0], ["a", "c", "e", "g"]);
+        assert_eq!(dropped, ['i']);
+    }
+}
@@ -0,0 +1,168 @@
+//! Stick calibration per controller to compensate drift and worn sticks.
+//!
+//! The calibration routine first records the resting position while the sticks are left
+//! untouched, then how far each stick can be tilted while the user rotates them.
+//! Results are saved to appdata for each controller serial.
+
+use crate::global::get_appdata_dir;
+use crate::input_method::HardKeyButton;
+use crate::ovr_controller::{ActionSetKind, OverlayPlane};
+use crate::{Application, ApplicationStatus, LeftRight};
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use std::{fs, io};
+
+// time to record the resting position
+const REST_DURATION: Duration = Duration::from_secs(2);
+// ranges smaller than this are considered as not rotated
+const MIN_RANGE: f32 = 0.3;
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub(crate) struct StickCalibration {
+    // the position reported while the stick is not touched
+    pub center: Vec2,
+    // the distance from the center to the edge of the stick
+    pub range: f32,
+}
+
+impl Default for StickCalibration {
+    fn default() -> Self {
+        Self {
+            center: Vec2::ZERO,
+            range: 1.0,
+        }
+    }
+}
+
+impl StickCalibration {
+    pub(crate) fn apply(&self, raw: Vec2) -> Vec2 {
+        ((raw - self.center) / self.range).clamp_length_max(1.0)
+    }
+}
+
+fn get_calibration_path() -> PathBuf {
+    get_appdata_dir().join("calibration.json")
+}
+
+fn do_load_calibrations() -> io::Result<HashMap<String, StickCalibration>> {
+    Ok(serde_json::from_reader(
+        File::open(get_calibration_path())?,
+    )?)
+}
+
+/// Returns calibrations keyed by controller serial.
+pub(crate) fn load_calibrations() -> HashMap<String, StickCalibration> {
+    match do_load_calibrations() {
+        Ok(calibrations) => calibrations,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => HashMap::new(),
+        Err(err) => {
+            log::error!("loading calibration: {err}");
+            HashMap::new()
+        }
+    }
+}
+
+fn write_calibrations(calibrations: &HashMap<String, StickCalibration>) -> io::Result<()> {
+    fs::create_dir_all(get_calibration_path().parent().unwrap())?;
+    let mut writing = File::create(get_calibration_path())?;
+    serde_json::to_writer_pretty(&mut writing, calibrations)?;
+    writing.flush()?;
+    Ok(())
+}
+
+////////////////////////////////////////
+
+pub(crate) struct Calibrating {
+    started: Instant,
+    // sum of positions and the number of samples while resting, for each hand
+    rest_sums: [Cell<(Vec2, u32)>; 2],
+    // the largest tilt from the center seen for each hand
+    ranges: [Cell<f32>; 2],
+}
+
+impl Calibrating {
+    pub(crate) fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            rest_sums: Default::default(),
+            ranges: Default::default(),
+        }
+    }
+
+    fn resting(&self) -> bool {
+        self.started.elapsed() < REST_DURATION
+    }
+
+    fn center(&self, hand: LeftRight) -> Vec2 {
+        let (sum, count) = self.rest_sums[hand as usize].get();
+        if count == 0 {
+            Vec2::ZERO
+        } else {
+            sum / count as f32
+        }
+    }
+
+    fn save(&self, app: &Application) {
+        let mut calibrations = load_calibrations();
+        for hand in [LeftRight::Left, LeftRight::Right] {
+            let range = self.ranges[hand as usize].get();
+            let Some(serial) = app.ovr_controller.controller_serial(hand) else {
+                log::warn!("controller not found; calibration is discarded");
+                continue;
+            };
+            if range < MIN_RANGE {
+                log::warn!("stick of {serial} is not rotated; calibration is discarded");
+                continue;
+            }
+            let calibration = StickCalibration {
+                center: self.center(hand),
+                range,
+            };
+            log::info!("calibration for {serial}: {calibration:?}");
+            calibrations.insert(serial, calibration);
+        }
+        if let Err(err) = write_calibrations(&calibrations) {
+            log::error!("saving calibration: {err}");
+        }
+        app.ovr_controller.reload_calibration();
+    }
+}
+
+impl ApplicationStatus for Calibrating {
+    fn tick(&self, app: &mut Application) {
+        app.ovr_controller.set_active_action_set([
+            ActionSetKind::Suspender,
+            ActionSetKind::Input,
+            ActionSetKind::Waiting,
+        ]);
+
+        app.ovr_controller.hide_overlay(OverlayPlane::Left);
+        app.ovr_controller.hide_overlay(OverlayPlane::Right);
+        app.ovr_controller.show_overlay(OverlayPlane::Center);
+
+        let resting = self.resting();
+        app.set_calibration_renderer(resting);
+        for hand in [LeftRight::Left, LeftRight::Right] {
+            let raw = app.ovr_controller.raw_stick_pos(hand);
+            if resting {
+                let sum = &self.rest_sums[hand as usize];
+                let (total, count) = sum.get();
+                sum.set((total + raw, count + 1));
+            } else {
+                let range = &self.ranges[hand as usize];
+                range.set(range.get().max((raw - self.center(hand)).length()));
+            }
+        }
+
+        if !resting && app.ovr_controller.click_started(HardKeyButton::CloseButton) {
+            self.save(app);
+            app.open_settings();
+        }
+    }
+}
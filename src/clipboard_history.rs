@@ -0,0 +1,127 @@
+//! History of the texts sent from the keyboard, shown as a plane to send them again.
+//!
+//! The newest text is on the first cell and a text sent again moves to the front.
+//! The history is kept in appdata with as many texts as a plane has cells, unless
+//! `saveClipboardHistory` is off.
+
+use crate::Application;
+use crate::config::CleKeyConfig;
+use crate::global::get_appdata_dir;
+use crate::haptics::HapticEvent;
+use crate::input_method::{CleKeyButton, CleKeyButtonAction, CleKeyInputTable, InputNextAction};
+use crate::table_file::{CELLS, RESERVED_CELLS};
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::PathBuf;
+use std::{fs, io};
+
+pub(crate) const CLIPBOARD_PLANE: &str = "clipboard";
+const MAX_ENTRIES: usize = CELLS - RESERVED_CELLS.len();
+// characters of a text shown on its cell
+const LABEL_LENGTH: usize = 6;
+// buttons leaked in a run; texts sent after that are not shown until the next run
+const MAX_BUTTONS: usize = 1024;
+
+#[derive(Default)]
+pub(crate) struct ClipboardHistory {
+    // newest first
+    entries: Vec<String>,
+    // buttons of the entries, leaked to be on a table like the builtin ones;
+    // never freed since the keyboard keeps copies of them past the plane
+    buttons: HashMap<String, CleKeyButton<'static>>,
+}
+
+fn get_history_path() -> PathBuf {
+    get_appdata_dir().join("clipboard_history.json")
+}
+
+impl ClipboardHistory {
+    pub(crate) fn load(config: &CleKeyConfig) -> Self {
+        if !config.save_clipboard_history {
+            return Self::default();
+        }
+        let entries = match File::open(get_history_path()) {
+            Ok(file) => serde_json::from_reader::<_, Vec<String>>(file).unwrap_or_else(|err| {
+                log::error!("loading clipboard history: {err}");
+                vec![]
+            }),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => vec![],
+            Err(err) => {
+                log::error!("loading clipboard history: {err}");
+                vec![]
+            }
+        };
+        Self {
+            entries: entries.into_iter().take(MAX_ENTRIES).collect(),
+            buttons: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn add(&mut self, text: &str, config: &CleKeyConfig) {
+        if text.is_empty() {
+            return;
+        }
+        self.entries.retain(|x| x != text);
+        self.entries.insert(0, text.to_owned());
+        self.entries.truncate(MAX_ENTRIES);
+        if config.save_clipboard_history
+            && let Err(err) = self.save()
+        {
+            log::error!("saving clipboard history: {err}");
+        }
+    }
+
+    fn save(&self) -> io::Result<()> {
+        fs::create_dir_all(get_history_path().parent().unwrap())?;
+        serde_json::to_writer(File::create(get_history_path())?, &self.entries)?;
+        Ok(())
+    }
+
+    fn button(&mut self, text: &str) -> Option<CleKeyButton<'static>> {
+        if let Some(&button) = self.buttons.get(text) {
+            return Some(button);
+        }
+        if self.buttons.len() == MAX_BUTTONS {
+            log::warn!("too many texts in the clipboard history to show them all");
+            return None;
+        }
+        let mut label = text.replace('\n', " ");
+        if label.chars().count() > LABEL_LENGTH {
+            label = label.chars().take(LABEL_LENGTH).chain(['…']).collect();
+        }
+        let button = CleKeyButton(Box::leak(Box::new([CleKeyButtonAction {
+            shows: Box::leak(label.into_boxed_str()),
+            action: InputNextAction::EnterText(Box::leak(text.to_owned().into_boxed_str())),
+        }])));
+        self.buttons.insert(text.to_owned(), button);
+        Some(button)
+    }
+
+    fn table(&mut self) -> CleKeyInputTable<'static> {
+        let mut table = [CleKeyButton::empty(); CELLS];
+        let mut cells = (0..CELLS).filter(|x| !RESERVED_CELLS.contains(x));
+        for idx in 0..self.entries.len() {
+            let text = self.entries[idx].clone();
+            if let Some(button) = self.button(&text) {
+                table[cells.next().unwrap()] = button;
+            }
+        }
+        CleKeyInputTable {
+            name: CLIPBOARD_PLANE,
+            starts_ime: false,
+            table,
+        }
+    }
+}
+
+impl Application<'_> {
+    pub(crate) fn swap_clipboard_plane(&mut self) {
+        self.play_haptic(HapticEvent::PlaneSwitch, None);
+        if self.kbd_status.method.name == CLIPBOARD_PLANE {
+            self.set_current_plane();
+        } else {
+            let table = self.clipboard.table();
+            self.set_plane(&table);
+        }
+    }
+}
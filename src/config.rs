@@ -59,8 +59,9 @@ macro_rules! merging_serde {
 
         #[doc(hidden)]
         const _: () = {
+            // pub(super) so that this can be used in submodules of config
             #[derive(serde::Deserialize)]
-            struct Partial {
+            pub(super) struct Partial {
                 $(
                 $(#[$field_attr])*
                 #[serde(default)]
@@ -82,6 +83,9 @@ macro_rules! merging_serde {
     () => {};
 }
 
+mod theme;
+pub use theme::*;
+
 merging_serde! {
     #[derive(Debug)]
     pub struct OverlayPositionConfig {
@@ -94,34 +98,18 @@ merging_serde! {
         #[serde(rename="widthRadio")]
         pub width_radio: f32,
         pub alpha: f32,
+        // what yaw, pitch and distance are relative to
+        pub anchor: OverlayAnchor,
     }
 
     #[derive(Debug)]
     pub struct RingOverlayConfig {
         pub position: OverlayPositionConfig,
-        #[serde(rename="centerColor", with="serialize_color4f_3f")]
-        pub center_color: ColorF,
-        #[serde(rename="backgroundColor", with="serialize_color4f_3f")]
-        pub background_color: ColorF,
-        #[serde(rename="edgeColor", with="serialize_color4f_3f")]
-        pub edge_color: ColorF,
-        #[serde(rename="normalCharColor", with="serialize_color4f_3f")]
-        pub normal_char_color: ColorF,
-        #[serde(rename="unSelectingCharColor", with="serialize_color4f_3f")]
-        pub un_selecting_char_color: ColorF,
-        #[serde(rename="selectingCharColor", with="serialize_color4f_3f")]
-        pub selecting_char_color: ColorF,
-        #[serde(rename="selectingCharInRingColor", with="serialize_color4f_3f")]
-        pub selecting_char_in_ring_color: ColorF,
     }
 
     #[derive(Debug)]
     pub struct CompletionOverlayConfig {
         pub position: OverlayPositionConfig,
-        #[serde(rename="backgroundColor", with="serialize_color4f_3f")]
-        pub background_color: ColorF,
-        #[serde(rename="inputtingCharColor", with="serialize_color4f_3f")]
-        pub inputting_char_color: ColorF,
     }
 
     #[derive(Debug)]
@@ -139,10 +127,147 @@ merging_serde! {
         pub completion: CompletionOverlayConfig,
     }
 
+    #[derive(Debug)]
+    pub struct OneHandMode {
+        pub ring: RingOverlayConfig,
+        pub completion: CompletionOverlayConfig,
+        // the stick used to choose both the row and the column
+        pub stick: StickSide,
+        // in ms; the chosen row is dropped after the stick rests this long
+        #[serde(rename="stepTimeout")]
+        pub step_timeout: u128,
+    }
+
+    #[derive(Debug)]
+    pub struct FlatMode {
+        // the center plane showing both the keys and the buffer
+        pub keyboard: CompletionOverlayConfig,
+        // the ring to choose conversion candidates
+        pub ring: RingOverlayConfig,
+    }
+
+    #[derive(Debug)]
+    pub struct StickConfig {
+        // tilt to start selecting, relative to the calibrated range
+        #[serde(rename="deadZone")]
+        pub dead_zone: f32,
+        // selection is kept until the tilt goes below deadZone - hysteresis
+        pub hysteresis: f32,
+        // in degree; rotates the sector borders clockwise
+        #[serde(rename="sectorOffset")]
+        pub sector_offset: f32,
+    }
+
+    #[derive(Debug)]
+    pub struct HapticsConfig {
+        pub enabled: bool,
+        // multiplied to the amplitude of every pulse
+        pub intensity: f32,
+        #[serde(rename="sectorChange")]
+        pub sector_change: HapticPulse,
+        #[serde(rename="multiTapStep")]
+        pub multi_tap_step: HapticPulse,
+        pub character: HapticPulse,
+        pub commit: HapticPulse,
+        pub backspace: HapticPulse,
+        #[serde(rename="planeSwitch")]
+        pub plane_switch: HapticPulse,
+        #[serde(rename="conversionFailure")]
+        pub conversion_failure: HapticPulse,
+        #[serde(rename="flushFailure")]
+        pub flush_failure: HapticPulse,
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct HapticPulse {
+        // in seconds
+        pub duration: f32,
+        pub frequency: f32,
+        pub amplitude: f32,
+        // number of pulses separated by the same duration
+        pub count: u32,
+    }
+
     #[derive(Debug)]
     pub struct Click {
         pub offset: u128,
         pub length: u128,
+        // how to choose one of the multiple characters on a button
+        pub selection: SelectionMode,
+        // in ms; taps within this time after the release cycle the characters
+        #[serde(rename="tapWindow")]
+        pub tap_window: u128,
+    }
+}
+
+/// Gestures used instead of the sticks and triggers on tracked hands.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HandTrackingMode {
+    /// Use the controllers.
+    #[default]
+    Off,
+    /// Bend the index finger to choose and pinch the thumb and middle finger to click.
+    Pointing,
+    /// Drag with the thumb and middle finger pinched to choose and pinch the index finger to click.
+    PinchDrag,
+}
+
+/// How one of the multiple characters on a button is chosen while clicking.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectionMode {
+    /// Cycles with the time the trigger is held.
+    #[default]
+    Time,
+    /// Deeper trigger pull chooses later characters.
+    Pull,
+    /// Each press of the trigger within the tap window chooses the next character.
+    TapCount,
+    /// Tracing the left stick through the sectors while holding the trigger inputs a word.
+    /// Only the left stick is read, even in the two ring mode. Planes without a dictionary
+    /// cycle with time instead.
+    Swipe,
+}
+
+/// The tracked object the overlay is placed relative to.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverlayAnchor {
+    /// Follows the head.
+    #[default]
+    Hmd,
+    /// Follows the left controller, like a wrist keyboard.
+    LeftController,
+    /// Follows the right controller.
+    RightController,
+    /// Stays in the room where it was when the keyboard is opened.
+    #[serde(alias = "standing")]
+    World,
+}
+
+impl OverlayPositionConfig {
+    /// The center of the overlay in HMD space.
+    pub fn point(&self) -> Vec3 {
+        let (yaw, pitch) = (self.yaw.to_radians(), self.pitch.to_radians());
+        Vec3::new(
+            -self.distance * pitch.cos() * yaw.sin(),
+            self.distance * pitch.sin(),
+            -self.distance * pitch.cos() * yaw.cos(),
+        )
+    }
+
+    /// Moves the overlay to the point in HMD space, keeping the width in meters.
+    pub fn set_point(&mut self, point: Vec3) {
+        let distance = point.length();
+        if distance < f32::EPSILON {
+            return;
+        }
+        let width = self.width_radio * self.distance;
+        self.yaw = (-point.x).atan2(-point.z).to_degrees();
+        self.pitch = (point.y / distance).asin().to_degrees();
+        self.distance = distance;
+        self.width_radio = width / distance;
     }
 }
 
@@ -154,10 +279,27 @@ pub struct CleKeyConfig {
     pub two_ring: TwoRingMode,
     #[serde(rename = "oneRing")]
     pub one_ring: OneRingMode,
+    pub flat: FlatMode,
+    #[serde(rename = "oneHand")]
+    pub one_hand: OneHandMode,
     pub click: Click,
+    pub stick: StickConfig,
+    pub haptics: HapticsConfig,
+    #[serde(rename = "handTracking")]
+    pub hand_tracking: HandTrackingMode,
     pub fps: f32,
     pub always_enter_paste: bool,
     pub always_use_buffer: bool,
+    // paste with the clipboard and restore it instead of typing the text
+    #[serde(rename = "restoreClipboard")]
+    pub restore_clipboard: bool,
+    // keep the clipboard history in appdata; otherwise it is kept only while running
+    #[serde(rename = "saveClipboardHistory")]
+    pub save_clipboard_history: bool,
+    pub theme: String,
+    pub snippets: Vec<Snippet>,
+    #[serde(rename = "outputRules")]
+    pub output_rules: Vec<OutputRule>,
 }
 
 impl Default for CleKeyConfig {
@@ -166,10 +308,26 @@ impl Default for CleKeyConfig {
             ui_mode: Default::default(),
             two_ring: Default::default(),
             one_ring: Default::default(),
+            flat: Default::default(),
+            one_hand: Default::default(),
             click: Default::default(),
+            stick: Default::default(),
+            haptics: Default::default(),
+            hand_tracking: Default::default(),
             fps: 72.0,
             always_enter_paste: false,
             always_use_buffer: true,
+            restore_clipboard: false,
+            save_clipboard_history: true,
+            theme: "light".to_owned(),
+            // prefixed so words typed as they are are not expanded
+            snippets: vec![
+                Snippet::new(";gg", "Good game, thanks for playing!"),
+                Snippet::new(";ty", "Thank you!"),
+                Snippet::new(";brb", "Be right back"),
+                Snippet::new(";now", "{date} {time}"),
+            ],
+            output_rules: vec![],
         }
     }
 }
@@ -188,7 +346,19 @@ const _: () = {
         #[serde(default)]
         pub one_ring: OptionalValue<OneRingMode>,
         #[serde(default)]
+        pub flat: OptionalValue<FlatMode>,
+        #[serde(rename = "oneHand")]
+        #[serde(default)]
+        pub one_hand: OptionalValue<OneHandMode>,
+        #[serde(default)]
         pub click: OptionalValue<Click>,
+        #[serde(default)]
+        pub stick: OptionalValue<StickConfig>,
+        #[serde(default)]
+        pub haptics: OptionalValue<HapticsConfig>,
+        #[serde(rename = "handTracking")]
+        #[serde(default)]
+        pub hand_tracking: OptionalValue<HandTrackingMode>,
         pub fps: OptionalValue<f32>,
 
         // old config
@@ -204,6 +374,19 @@ const _: () = {
         pub always_enter_paste: OptionalValue<bool>,
         #[serde(default)]
         pub always_use_buffer: OptionalValue<bool>,
+        #[serde(rename = "restoreClipboard")]
+        #[serde(default)]
+        pub restore_clipboard: OptionalValue<bool>,
+        #[serde(rename = "saveClipboardHistory")]
+        #[serde(default)]
+        pub save_clipboard_history: OptionalValue<bool>,
+        #[serde(default)]
+        pub theme: OptionalValue<String>,
+        #[serde(default)]
+        pub snippets: OptionalValue<Vec<Snippet>>,
+        #[serde(rename = "outputRules")]
+        #[serde(default)]
+        pub output_rules: OptionalValue<Vec<OutputRule>>,
     }
 
     impl MergeSerialize for CleKeyConfig {
@@ -223,7 +406,12 @@ const _: () = {
             partial.ui_mode.merge_value(&mut self.ui_mode);
             partial.two_ring.merge_value(&mut self.two_ring);
             partial.one_ring.merge_value(&mut self.one_ring);
+            partial.flat.merge_value(&mut self.flat);
+            partial.one_hand.merge_value(&mut self.one_hand);
             partial.click.merge_value(&mut self.click);
+            partial.stick.merge_value(&mut self.stick);
+            partial.haptics.merge_value(&mut self.haptics);
+            partial.hand_tracking.merge_value(&mut self.hand_tracking);
             partial.fps.merge_value(&mut self.fps);
             partial
                 .always_enter_paste
@@ -231,6 +419,15 @@ const _: () = {
             partial
                 .always_use_buffer
                 .merge_value(&mut self.always_use_buffer);
+            partial
+                .restore_clipboard
+                .merge_value(&mut self.restore_clipboard);
+            partial
+                .save_clipboard_history
+                .merge_value(&mut self.save_clipboard_history);
+            partial.theme.merge_value(&mut self.theme);
+            partial.snippets.merge_value(&mut self.snippets);
+            partial.output_rules.merge_value(&mut self.output_rules);
         }
     }
 };
@@ -240,23 +437,88 @@ pub enum UIMode {
     TwoRing,
     #[default]
     OneRing,
+    // flat keyboard pressed with the laser pointer
+    Flat,
+    // one stick chooses the row and then the column
+    OneHand,
 }
 
-mod serialize_color4f_3f {
+/// A text input by its abbreviation, with `{date}` and `{time}` filled in.
+/// Any word can be an abbreviation, so one starting with a sign like `;` avoids expanding
+/// a word meant as it is.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Snippet {
+    pub abbreviation: String,
+    pub text: String,
+}
+
+impl Snippet {
+    fn new(abbreviation: &str, text: &str) -> Self {
+        Self {
+            abbreviation: abbreviation.to_owned(),
+            text: text.to_owned(),
+        }
+    }
+}
+
+/// How text is sent to the application.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputMethod {
+    /// Virtual key events for each character where possible.
+    KeyEvents,
+    /// Unicode key events.
+    Unicode,
+    /// Pasting with the clipboard, restoring it after.
+    Paste,
+    /// Only copying to the clipboard.
+    CopyOnly,
+}
+
+/// Output settings for the application whose window class or process name is `app`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct OutputRule {
+    pub app: String,
+    pub output: OutputMethod,
+    #[serde(rename = "alwaysEnterPaste")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub always_enter_paste: Option<bool>,
+}
+
+/// The controller used in the one hand mode.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StickSide {
+    Left,
+    #[default]
+    Right,
+}
+
+mod serialize_color4f {
     use super::OptionalValue;
     use pathfinder_color::ColorF;
+    use serde::de::Error;
     use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
     pub fn serialize<S: Serializer>(value: &ColorF, serializer: S) -> Result<S::Ok, S::Error> {
-        Serialize::serialize(&[value.r(), value.g(), value.b()], serializer)
+        Serialize::serialize(&[value.r(), value.g(), value.b(), value.a()], serializer)
     }
 
     pub(super) fn deserialize<'de, D: Deserializer<'de>>(
         deserializer: D,
     ) -> Result<OptionalValue<ColorF>, D::Error> {
-        <[f32; 3] as Deserialize>::deserialize(deserializer)
-            .map(|[r, g, b]| ColorF::new(r, g, b, 1.0))
-            .map(OptionalValue::Value)
+        deserialize_color(deserializer).map(OptionalValue::Value)
+    }
+
+    // accepts both [r, g, b] (alpha = 1) and [r, g, b, a]
+    pub(super) fn deserialize_color<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<ColorF, D::Error> {
+        match <Vec<f32> as Deserialize>::deserialize(deserializer)?.as_slice() {
+            &[r, g, b] => Ok(ColorF::new(r, g, b, 1.0)),
+            &[r, g, b, a] => Ok(ColorF::new(r, g, b, a)),
+            other => Err(D::Error::invalid_length(other.len(), &"3 or 4 components")),
+        }
     }
 }
 
@@ -270,8 +532,8 @@ impl Default for TwoRingMode {
                     distance: 0.75,
                     width_radio: 0.2,
                     alpha: 1.0,
+                    anchor: OverlayAnchor::Hmd,
                 },
-                ..Default::default()
             },
             right_ring: RingOverlayConfig {
                 position: OverlayPositionConfig {
@@ -280,8 +542,8 @@ impl Default for TwoRingMode {
                     distance: 0.75,
                     width_radio: 0.2,
                     alpha: 1.0,
+                    anchor: OverlayAnchor::Hmd,
                 },
-                ..Default::default()
             },
             completion: CompletionOverlayConfig {
                 position: OverlayPositionConfig {
@@ -290,9 +552,8 @@ impl Default for TwoRingMode {
                     distance: 0.75,
                     width_radio: 0.333,
                     alpha: 1.0,
+                    anchor: OverlayAnchor::Hmd,
                 },
-                background_color: ColorF::new(0.188, 0.345, 0.749, 1.0),
-                inputting_char_color: ColorF::new(1.0, 0.0, 0.0, 1.0),
             },
         }
     }
@@ -308,8 +569,8 @@ impl Default for OneRingMode {
                     distance: 0.75,
                     width_radio: 0.3,
                     alpha: 1.0,
+                    anchor: OverlayAnchor::Hmd,
                 },
-                ..Default::default()
             },
             completion: CompletionOverlayConfig {
                 position: OverlayPositionConfig {
@@ -318,9 +579,47 @@ impl Default for OneRingMode {
                     distance: 0.75,
                     width_radio: 0.333,
                     alpha: 1.0,
+                    anchor: OverlayAnchor::Hmd,
+                },
+            },
+        }
+    }
+}
+
+impl Default for OneHandMode {
+    fn default() -> Self {
+        let one_ring = OneRingMode::default();
+        Self {
+            ring: one_ring.ring,
+            completion: one_ring.completion,
+            stick: StickSide::Right,
+            step_timeout: 1500,
+        }
+    }
+}
+
+impl Default for FlatMode {
+    fn default() -> Self {
+        Self {
+            keyboard: CompletionOverlayConfig {
+                position: OverlayPositionConfig {
+                    yaw: 0.0,
+                    pitch: -30.0,
+                    distance: 0.6,
+                    width_radio: 0.8,
+                    alpha: 1.0,
+                    anchor: OverlayAnchor::Hmd,
+                },
+            },
+            ring: RingOverlayConfig {
+                position: OverlayPositionConfig {
+                    yaw: 0.0,
+                    pitch: -10.0,
+                    distance: 0.75,
+                    width_radio: 0.3,
+                    alpha: 1.0,
+                    anchor: OverlayAnchor::Hmd,
                 },
-                background_color: ColorF::new(0.188, 0.345, 0.749, 1.0),
-                inputting_char_color: ColorF::new(1.0, 0.0, 0.0, 1.0),
             },
         }
     }
@@ -331,27 +630,46 @@ impl Default for Click {
         Self {
             offset: 120,
             length: 300,
+            selection: SelectionMode::Time,
+            tap_window: 400,
         }
     }
 }
 
-impl Default for RingOverlayConfig {
+impl Default for StickConfig {
     fn default() -> Self {
         Self {
-            position: OverlayPositionConfig {
-                yaw: 0.0,
-                pitch: 0.0,
-                distance: 0.0,
-                width_radio: 0.0,
-                alpha: 0.0,
-            },
-            center_color: ColorF::new(0.83, 0.83, 0.83, 1.0),
-            background_color: ColorF::new(0.686, 0.686, 0.686, 1.0),
-            edge_color: ColorF::new(1.0, 1.0, 1.0, 1.0),
-            normal_char_color: ColorF::new(0.0, 0.0, 0.0, 1.0),
-            un_selecting_char_color: ColorF::new(0.5, 0.5, 0.5, 1.0),
-            selecting_char_color: ColorF::new(0.0, 0.0, 0.0, 1.0),
-            selecting_char_in_ring_color: ColorF::new(1.0, 0.0, 0.0, 1.0),
+            dead_zone: 0.8,
+            hysteresis: 0.05,
+            sector_offset: 0.0,
+        }
+    }
+}
+
+impl Default for HapticsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            intensity: 1.0,
+            sector_change: HapticPulse::new(0.05, 0.5, 1),
+            multi_tap_step: HapticPulse::new(0.02, 0.3, 1),
+            character: HapticPulse::new(0.01, 0.2, 1),
+            commit: HapticPulse::new(0.03, 0.4, 1),
+            backspace: HapticPulse::new(0.04, 0.6, 1),
+            plane_switch: HapticPulse::new(0.08, 0.5, 1),
+            conversion_failure: HapticPulse::new(0.1, 1.0, 2),
+            flush_failure: HapticPulse::new(0.1, 1.0, 3),
+        }
+    }
+}
+
+impl HapticPulse {
+    const fn new(duration: f32, amplitude: f32, count: u32) -> Self {
+        Self {
+            duration,
+            frequency: 1.0,
+            amplitude,
+            count,
         }
     }
 }
@@ -366,11 +684,16 @@ fn do_load_config(config: &mut CleKeyConfig) -> io::Result<()> {
     let config_path = get_config_path();
     let config_file = File::open(config_path)?;
 
-    CleKeyConfig::merge(config, serde_json::from_reader(config_file)?);
+    let json: serde_json::Value = serde_json::from_reader(config_file)?;
+    CleKeyConfig::merge(config, serde_json::from_value(json.clone())?);
+    // colors were in the overlay configs before themes
+    if let Some(theme) = theme::migrate_legacy_colors(&json, &config.theme) {
+        config.theme = theme;
+    }
     Ok(())
 }
 
-fn write_config(config: &CleKeyConfig) -> io::Result<()> {
+pub fn write_config(config: &CleKeyConfig) -> io::Result<()> {
     fs::create_dir_all(get_config_path().parent().unwrap())?;
     let mut writing = File::create(get_config_path())?;
     serde_json::to_writer_pretty(&mut writing, config)?;
@@ -419,5 +742,11 @@ impl MergeSerializePrimitive for Vec4 {}
 impl MergeSerializePrimitive for String {}
 impl MergeSerializePrimitive for ColorF {}
 impl MergeSerializePrimitive for UIMode {}
+impl MergeSerializePrimitive for OverlayAnchor {}
+impl MergeSerializePrimitive for SelectionMode {}
+impl MergeSerializePrimitive for HandTrackingMode {}
+impl MergeSerializePrimitive for StickSide {}
+impl MergeSerializePrimitive for Vec<Snippet> {}
+impl MergeSerializePrimitive for Vec<OutputRule> {}
 
 ////////////////////////////////////////
@@ -0,0 +1,371 @@
+use super::serialize_color4f::deserialize_color;
+use super::{MergeSerialize, MergeSerializePrimitive, OptionalValue};
+use crate::global::{get_appdata_dir, get_resources_dir};
+use pathfinder_color::ColorF;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value, json};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+use std::{fs, io};
+
+merging_serde! {
+    #[derive(Debug, Clone)]
+    pub struct RingTheme {
+        #[serde(rename="centerColor", with="super::serialize_color4f")]
+        pub center_color: ColorF,
+        pub background: Gradient,
+        #[serde(rename="edgeColor", with="super::serialize_color4f")]
+        pub edge_color: ColorF,
+        #[serde(rename="normalCharColor", with="super::serialize_color4f")]
+        pub normal_char_color: ColorF,
+        #[serde(rename="unSelectingCharColor", with="super::serialize_color4f")]
+        pub un_selecting_char_color: ColorF,
+        #[serde(rename="selectingCharColor", with="super::serialize_color4f")]
+        pub selecting_char_color: ColorF,
+        #[serde(rename="selectingCharInRingColor", with="super::serialize_color4f")]
+        pub selecting_char_in_ring_color: ColorF,
+        #[serde(rename="cursorColor", with="super::serialize_color4f")]
+        pub cursor_color: ColorF,
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct CompletionTheme {
+        #[serde(rename="backgroundColor", with="super::serialize_color4f")]
+        pub background_color: ColorF,
+        #[serde(rename="inputtingCharColor", with="super::serialize_color4f")]
+        pub inputting_char_color: ColorF,
+        #[serde(rename="candidateCharColor", with="super::serialize_color4f")]
+        pub candidate_char_color: ColorF,
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct Theme {
+        pub ring: RingTheme,
+        // the rings of the two ring mode; `ring` of a theme file applies to them too and
+        // `leftRing` or `rightRing` override it
+        #[serde(rename="leftRing")]
+        pub left_ring: RingTheme,
+        #[serde(rename="rightRing")]
+        pub right_ring: RingTheme,
+        pub completion: CompletionTheme,
+        // plane name -> accent color used for the ring edges
+        pub accents: PlaneAccents,
+    }
+}
+
+impl Theme {
+    pub fn accent_color(&self, plane: &str) -> ColorF {
+        self.accents
+            .0
+            .get(plane)
+            .copied()
+            .unwrap_or(self.ring.edge_color)
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        let ring = RingTheme {
+            center_color: ColorF::new(0.83, 0.83, 0.83, 1.0),
+            background: Gradient::solid(ColorF::new(0.686, 0.686, 0.686, 1.0)),
+            edge_color: ColorF::new(1.0, 1.0, 1.0, 1.0),
+            normal_char_color: ColorF::new(0.0, 0.0, 0.0, 1.0),
+            un_selecting_char_color: ColorF::new(0.5, 0.5, 0.5, 1.0),
+            selecting_char_color: ColorF::new(0.0, 0.0, 0.0, 1.0),
+            selecting_char_in_ring_color: ColorF::new(1.0, 0.0, 0.0, 1.0),
+            cursor_color: ColorF::new(0.22, 0.22, 0.22, 1.0),
+        };
+        Self {
+            left_ring: ring.clone(),
+            right_ring: ring.clone(),
+            ring,
+            completion: CompletionTheme {
+                background_color: ColorF::new(0.188, 0.345, 0.749, 1.0),
+                inputting_char_color: ColorF::new(1.0, 0.0, 0.0, 1.0),
+                candidate_char_color: ColorF::new(0.0, 0.0, 0.0, 1.0),
+            },
+            accents: PlaneAccents::default(),
+        }
+    }
+}
+
+/// Radial gradient from the inner edge to the outer edge of the ring background.
+///
+/// In json, this can be written as a single color for solid backgrounds.
+#[derive(Debug, Clone, Copy)]
+pub struct Gradient {
+    pub inner: ColorF,
+    pub outer: ColorF,
+}
+
+impl Gradient {
+    pub const fn solid(color: ColorF) -> Self {
+        Self {
+            inner: color,
+            outer: color,
+        }
+    }
+}
+
+impl Serialize for Gradient {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Repr<'a> {
+            #[serde(with = "super::serialize_color4f")]
+            inner: &'a ColorF,
+            #[serde(with = "super::serialize_color4f")]
+            outer: &'a ColorF,
+        }
+
+        Repr {
+            inner: &self.inner,
+            outer: &self.outer,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Gradient {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct Color(#[serde(deserialize_with = "deserialize_color")] ColorF);
+
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Solid(Color),
+            Gradient { inner: Color, outer: Color },
+        }
+
+        Ok(match Repr::deserialize(deserializer)? {
+            Repr::Solid(color) => Gradient::solid(color.0),
+            Repr::Gradient { inner, outer } => Gradient {
+                inner: inner.0,
+                outer: outer.0,
+            },
+        })
+    }
+}
+
+impl MergeSerializePrimitive for Gradient {}
+
+#[derive(Debug, Clone, Default)]
+pub struct PlaneAccents(HashMap<String, ColorF>);
+
+impl Serialize for PlaneAccents {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(
+            self.0
+                .iter()
+                .map(|(plane, color)| (plane, [color.r(), color.g(), color.b(), color.a()])),
+        )
+    }
+}
+
+impl<'de> Deserialize<'de> for PlaneAccents {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct Color(#[serde(deserialize_with = "deserialize_color")] ColorF);
+
+        let map = HashMap::<String, Color>::deserialize(deserializer)?;
+        Ok(Self(map.into_iter().map(|(k, v)| (k, v.0)).collect()))
+    }
+}
+
+// accents are merged per plane so extending theme can override some of them
+impl MergeSerialize for PlaneAccents {
+    type PartialType = PlaneAccents;
+
+    fn merge(&mut self, partial: Self::PartialType) {
+        self.0.extend(partial.0);
+    }
+}
+
+////////////////////////////////////////
+
+const MAX_EXTENDS_DEPTH: usize = 8;
+
+#[derive(Deserialize)]
+struct ThemeFile {
+    // name of the theme this theme is based on
+    #[serde(default)]
+    extends: Option<String>,
+    #[serde(flatten)]
+    theme: <Theme as MergeSerialize>::PartialType,
+}
+
+// user themes in appdata take precedence over bundled ones
+fn find_theme(name: &str) -> Option<PathBuf> {
+    let file_name = format!("{name}.json");
+    [
+        get_appdata_dir().join("themes").join(&file_name),
+        get_resources_dir().join("themes").join(&file_name),
+    ]
+    .into_iter()
+    .find(|path| path.is_file())
+}
+
+fn do_load_theme(theme: &mut Theme, name: &str, depth: usize) -> io::Result<()> {
+    if depth > MAX_EXTENDS_DEPTH {
+        return Err(io::Error::other(format!(
+            "theme {name}: too deep extends chain"
+        )));
+    }
+    let path = find_theme(name).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, format!("theme {name} not found"))
+    })?;
+    let mut file: Value = serde_json::from_reader(File::open(path)?)?;
+    share_ring_colors(&mut file);
+    let file: ThemeFile = serde_json::from_value(file)?;
+    if let Some(base) = &file.extends {
+        do_load_theme(theme, base, depth + 1)?;
+    }
+    theme.merge(file.theme);
+    Ok(())
+}
+
+// the colors of `ring` are the base of `leftRing` and `rightRing`
+fn share_ring_colors(file: &mut Value) {
+    let Some(ring) = file.get("ring").and_then(Value::as_object).cloned() else {
+        return;
+    };
+    for key in ["leftRing", "rightRing"] {
+        let mut colors = ring.clone();
+        if let Some(own) = file.get(key).and_then(Value::as_object) {
+            colors.extend(own.clone());
+        }
+        file[key] = Value::Object(colors);
+    }
+}
+
+/// Returns names of all themes in appdata and bundled resources, sorted.
+pub fn list_themes() -> Vec<String> {
+    let mut names = [
+        get_appdata_dir().join("themes"),
+        get_resources_dir().join("themes"),
+    ]
+    .iter()
+    .filter_map(|dir| fs::read_dir(dir).ok())
+    .flatten()
+    .filter_map(|entry| {
+        let path = entry.ok()?.path();
+        if path.extension()? != "json" {
+            return None;
+        }
+        Some(path.file_stem()?.to_string_lossy().into_owned())
+    })
+    .collect::<Vec<_>>();
+    names.sort();
+    names.dedup();
+    names
+}
+
+pub fn load_theme(name: &str) -> Theme {
+    let mut theme = Theme::default();
+    if let Err(err) = do_load_theme(&mut theme, name, 0) {
+        log::error!("loading theme: {err}");
+    }
+    theme
+}
+
+////////////////////////////////////////
+
+// the theme made from the colors in the overlay configs of old config files
+const LEGACY_THEME: &str = "legacy";
+
+// color keys of the old overlay configs and the keys in the theme
+const LEGACY_RING_COLORS: [(&str, &str); 7] = [
+    ("centerColor", "centerColor"),
+    ("backgroundColor", "background"),
+    ("edgeColor", "edgeColor"),
+    ("normalCharColor", "normalCharColor"),
+    ("unSelectingCharColor", "unSelectingCharColor"),
+    ("selectingCharColor", "selectingCharColor"),
+    ("selectingCharInRingColor", "selectingCharInRingColor"),
+];
+const LEGACY_COMPLETION_COLORS: [(&str, &str); 2] = [
+    ("backgroundColor", "backgroundColor"),
+    ("inputtingCharColor", "inputtingCharColor"),
+];
+
+// the first color found for each key in the overlays at the paths
+fn collect_colors(config: &Value, paths: &[&[&str]], keys: &[(&str, &str)]) -> Map<String, Value> {
+    let mut colors = Map::new();
+    for path in paths {
+        let overlay = path.iter().try_fold(config, |value, key| value.get(key));
+        for (old, new) in keys {
+            if let Some(color) = overlay.and_then(|x| x.get(old)) {
+                colors.entry(*new).or_insert_with(|| color.clone());
+            }
+        }
+    }
+    colors
+}
+
+fn write_legacy_theme(theme: &Value) -> io::Result<()> {
+    let dir = get_appdata_dir().join("themes");
+    fs::create_dir_all(&dir)?;
+    let mut writing = File::create(dir.join(format!("{LEGACY_THEME}.json")))?;
+    serde_json::to_writer_pretty(&mut writing, theme)?;
+    writing.flush()?;
+    Ok(())
+}
+
+/// Moves the colors of the old overlay configs into a theme extending `base`.
+///
+/// Returns the name of the theme to use, or None if the config has no old colors.
+pub(super) fn migrate_legacy_colors(config: &Value, base: &str) -> Option<String> {
+    let ring = collect_colors(
+        config,
+        &[
+            &["oneRing", "ring"],
+            &["leftRing"],
+            &["twoRing", "leftRing"],
+        ],
+        &LEGACY_RING_COLORS,
+    );
+    // the rings of the two ring mode could be colored apart
+    let left_ring = collect_colors(
+        config,
+        &[&["leftRing"], &["twoRing", "leftRing"]],
+        &LEGACY_RING_COLORS,
+    );
+    let right_ring = collect_colors(
+        config,
+        &[&["rightRing"], &["twoRing", "rightRing"]],
+        &LEGACY_RING_COLORS,
+    );
+    let completion = collect_colors(
+        config,
+        &[
+            &["completion"],
+            &["twoRing", "completion"],
+            &["oneRing", "completion"],
+        ],
+        &LEGACY_COMPLETION_COLORS,
+    );
+    if [&ring, &left_ring, &right_ring, &completion]
+        .iter()
+        .all(|x| x.is_empty())
+    {
+        return None;
+    }
+
+    let mut theme = json!({
+        "ring": ring,
+        "leftRing": left_ring,
+        "rightRing": right_ring,
+        "completion": completion,
+    });
+    if base != LEGACY_THEME {
+        theme["extends"] = json!(base);
+    }
+    if let Err(err) = write_legacy_theme(&theme) {
+        log::error!("colors in config.json are ignored; saving them as a theme: {err}");
+        return None;
+    }
+    log::warn!("colors in config.json are moved to the theme {LEGACY_THEME}");
+    Some(LEGACY_THEME.to_owned())
+}
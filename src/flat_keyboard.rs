@@ -0,0 +1,196 @@
+//! Flat QWERTY keyboard on the center overlay, pressed with the SteamVR laser pointer.
+//!
+//! The buffer lane at the top is shared with the ring modes and the keys are laid out below it.
+//! Conversion candidates are chosen with the ring as in the one ring mode.
+
+use crate::Application;
+use crate::input_method::InputNextAction;
+use crate::ovr_controller::{MouseEventKind, OverlayPlane};
+use glam::Vec2;
+
+// in overlay coordinates, from -1 to 1
+const TOP: f32 = 1.0 - 0.36;
+const BOTTOM: f32 = -1.0;
+const ROW_UNITS: f32 = 10.0;
+const KEY_MARGIN: f32 = 0.01;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub(crate) enum FlatKey {
+    // the character and the one with shift
+    Char(char, char),
+    Backspace,
+    CursorLeft,
+    CursorRight,
+    Shift,
+    Enter,
+    Space,
+    Convert,
+    Close,
+}
+
+use FlatKey::*;
+
+// keys with their width in units; each row is ROW_UNITS wide
+static ROWS: &[&[(FlatKey, f32)]] = &[
+    &[
+        (Char('1', '!'), 1.0),
+        (Char('2', '@'), 1.0),
+        (Char('3', '#'), 1.0),
+        (Char('4', '$'), 1.0),
+        (Char('5', '%'), 1.0),
+        (Char('6', '^'), 1.0),
+        (Char('7', '&'), 1.0),
+        (Char('8', '*'), 1.0),
+        (Char('9', '('), 1.0),
+        (Char('0', ')'), 1.0),
+    ],
+    &[
+        (Char('q', 'Q'), 1.0),
+        (Char('w', 'W'), 1.0),
+        (Char('e', 'E'), 1.0),
+        (Char('r', 'R'), 1.0),
+        (Char('t', 'T'), 1.0),
+        (Char('y', 'Y'), 1.0),
+        (Char('u', 'U'), 1.0),
+        (Char('i', 'I'), 1.0),
+        (Char('o', 'O'), 1.0),
+        (Char('p', 'P'), 1.0),
+    ],
+    &[
+        (Char('a', 'A'), 1.0),
+        (Char('s', 'S'), 1.0),
+        (Char('d', 'D'), 1.0),
+        (Char('f', 'F'), 1.0),
+        (Char('g', 'G'), 1.0),
+        (Char('h', 'H'), 1.0),
+        (Char('j', 'J'), 1.0),
+        (Char('k', 'K'), 1.0),
+        (Char('l', 'L'), 1.0),
+        (Backspace, 1.0),
+    ],
+    &[
+        (Shift, 1.5),
+        (Char('z', 'Z'), 1.0),
+        (Char('x', 'X'), 1.0),
+        (Char('c', 'C'), 1.0),
+        (Char('v', 'V'), 1.0),
+        (Char('b', 'B'), 1.0),
+        (Char('n', 'N'), 1.0),
+        (Char('m', 'M'), 1.0),
+        (Enter, 1.5),
+    ],
+    &[
+        (Close, 1.5),
+        (Convert, 1.5),
+        (Space, 3.0),
+        (CursorLeft, 1.0),
+        (CursorRight, 1.0),
+        (Char(',', '<'), 1.0),
+        (Char('.', '>'), 1.0),
+    ],
+];
+
+impl FlatKey {
+    pub(crate) fn label(self, shift: bool) -> String {
+        match self {
+            Char(c, _) if !shift => c.to_string(),
+            Char(_, c) => c.to_string(),
+            Backspace => "⌫".to_owned(),
+            CursorLeft => "←".to_owned(),
+            CursorRight => "→".to_owned(),
+            Shift => "⇧".to_owned(),
+            Enter => "⏎".to_owned(),
+            Space => "␣".to_owned(),
+            Convert => "変換".to_owned(),
+            Close => "Close".to_owned(),
+        }
+    }
+}
+
+/// Returns each key with its bottom left corner and size, in overlay coordinates.
+pub(crate) fn key_rects() -> impl Iterator<Item = (FlatKey, Vec2, Vec2)> {
+    let row_height = (TOP - BOTTOM) / ROWS.len() as f32;
+    let unit = 2.0 / ROW_UNITS;
+    ROWS.iter().enumerate().flat_map(move |(row, keys)| {
+        let y = TOP - row_height * (row + 1) as f32;
+        let mut x = -1.0;
+        keys.iter().map(move |&(key, width)| {
+            let origin = Vec2::new(x, y) + KEY_MARGIN;
+            let size = Vec2::new(unit * width, row_height) - KEY_MARGIN * 2.0;
+            x += unit * width;
+            (key, origin, size)
+        })
+    })
+}
+
+// position is from (0, 0) to (1, 1) as the mouse events report
+fn key_at(position: Vec2) -> Option<FlatKey> {
+    let position = position * 2.0 - 1.0;
+    key_rects()
+        .find(|&(_, origin, size)| {
+            let offset = position - origin;
+            offset.cmpge(Vec2::ZERO).all() && offset.cmple(size).all()
+        })
+        .map(|(key, _, _)| key)
+}
+
+#[derive(Default)]
+pub(crate) struct FlatKeyboard {
+    pub(crate) hovered: Option<FlatKey>,
+    // the key the pointer button went down on
+    pressed: Option<FlatKey>,
+    pub(crate) shift: bool,
+}
+
+impl Application<'_> {
+    /// Handles the laser pointer on the flat keyboard; returns true if the keyboard should be closed.
+    pub(crate) fn flat_keyboard_tick(&mut self) -> bool {
+        while let Some(event) = self.ovr_controller.next_mouse_event(OverlayPlane::Center) {
+            let key = key_at(event.position);
+            self.flat_keyboard.hovered = key;
+            match event.kind {
+                MouseEventKind::Move => {}
+                MouseEventKind::Down => self.flat_keyboard.pressed = key,
+                MouseEventKind::Up => {
+                    // the key is pressed only if the pointer did not leave it
+                    if let Some(pressed) = self.flat_keyboard.pressed.take()
+                        && Some(pressed) == key
+                        && self.press_flat_key(pressed)
+                    {
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    fn press_flat_key(&mut self, key: FlatKey) -> bool {
+        match key {
+            Char(c, shifted) => {
+                let c = if self.flat_keyboard.shift { shifted } else { c };
+                self.flat_keyboard.shift = false;
+                self.do_input_action(&InputNextAction::EnterChar(c));
+            }
+            Backspace => Self::backspace_key(self),
+            CursorLeft => Self::cursor_left_key(self),
+            CursorRight => Self::cursor_right_key(self),
+            Shift => self.flat_keyboard.shift = !self.flat_keyboard.shift,
+            // shift enter breaks the line in the buffer
+            Enter if self.flat_keyboard.shift => {
+                self.flat_keyboard.shift = false;
+                Self::new_line_key(self)
+            }
+            Enter => Self::enter_key(self),
+            Space => Self::space_key(self),
+            Convert => {
+                if !self.kbd_status.buffer.is_empty() {
+                    Self::henkan_key(self)
+                }
+            }
+            // the buffer is sent before closing, and kept open if it could not be
+            Close => return self.kbd_status.buffer.is_empty() || self.flush(false),
+        }
+        false
+    }
+}
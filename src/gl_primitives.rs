@@ -218,7 +218,8 @@ impl RectangleRenderer {
 pub struct BaseBackgroundRenderer {
     base: ShaderRenderer,
     center_color_uniform: GLint,
-    bg_color_uniform: GLint,
+    bg_inner_color_uniform: GLint,
+    bg_outer_color_uniform: GLint,
     line_color_uniform: GLint,
 }
 
@@ -233,9 +234,10 @@ impl BaseBackgroundRenderer {
                 in vec2 v2f_uv;\n\
                 out vec4 out_color;\n\
                 \n\
-                uniform vec3 center_color;\n\
-                uniform vec3 bg_color;\n\
-                uniform vec3 line_color;\n\
+                uniform vec4 center_color;\n\
+                uniform vec4 bg_inner_color;\n\
+                uniform vec4 bg_outer_color;\n\
+                uniform vec4 line_color;\n\
                 \n\
                 float d_from_line(float angle_deg) {\n\
                     float angle_rad = angle_deg * PI / 180;\n\
@@ -254,11 +256,14 @@ impl BaseBackgroundRenderer {
                     \n\
                     float min_line_d = min(min(d1, d2), min(d3, d4));\n\
                     \n\
-                    out_color.rgb = rsq < CENTER * CENTER ? center_color \
+                    float t = clamp((sqrt(rsq) - CENTER) / (1 - CENTER), 0, 1);\n\
+                    vec4 bg_color = mix(bg_inner_color, bg_outer_color, t);\n\
+                    \n\
+                    out_color = rsq < CENTER * CENTER ? center_color \
                             : min_line_d < (LINE_W / 2) || (rsq > ((1 - LINE_W) * (1 - LINE_W))) ? line_color \
                             : bg_color;\n\
                     \n\
-                    out_color.a = step(rsq, 1.0);\n\
+                    out_color.a *= step(rsq, 1.0);\n\
                 }\n\
                 \n\
                 ",
@@ -266,51 +271,44 @@ impl BaseBackgroundRenderer {
         unsafe {
             let center_color_uniform =
                 gl::GetUniformLocation(base.shader_program, c"center_color".as_ptr());
-            let bg_color_uniform =
-                gl::GetUniformLocation(base.shader_program, c"bg_color".as_ptr());
+            let bg_inner_color_uniform =
+                gl::GetUniformLocation(base.shader_program, c"bg_inner_color".as_ptr());
+            let bg_outer_color_uniform =
+                gl::GetUniformLocation(base.shader_program, c"bg_outer_color".as_ptr());
             let line_color_uniform =
                 gl::GetUniformLocation(base.shader_program, c"line_color".as_ptr());
-            assert!(bg_color_uniform != -1, "bg_color not found");
+            assert!(bg_inner_color_uniform != -1, "bg_inner_color not found");
+            assert!(bg_outer_color_uniform != -1, "bg_outer_color not found");
             assert!(line_color_uniform != -1, "line_color not found");
 
             Self {
                 base,
                 center_color_uniform,
-                bg_color_uniform,
+                bg_inner_color_uniform,
+                bg_outer_color_uniform,
                 line_color_uniform,
             }
         }
     }
 
-    /// Renders glyphs in specified color.
+    /// Renders ring background. bg colors are interpolated from inner to outer edge.
     pub fn draw(
         &self,
         transform: Transform2F,
         center_color: ColorF,
-        bg_color: ColorF,
+        bg_inner_color: ColorF,
+        bg_outer_color: ColorF,
         line_color: ColorF,
     ) {
-        self.base.draw(transform, || unsafe {
-            gl::Uniform3f(
-                self.center_color_uniform,
-                center_color.r(),
-                center_color.g(),
-                center_color.b(),
-            );
-
-            gl::Uniform3f(
-                self.bg_color_uniform,
-                bg_color.r(),
-                bg_color.g(),
-                bg_color.b(),
-            );
+        fn uniform_color(location: GLint, color: ColorF) {
+            unsafe { gl::Uniform4f(location, color.r(), color.g(), color.b(), color.a()) }
+        }
 
-            gl::Uniform3f(
-                self.line_color_uniform,
-                line_color.r(),
-                line_color.g(),
-                line_color.b(),
-            );
+        self.base.draw(transform, || {
+            uniform_color(self.center_color_uniform, center_color);
+            uniform_color(self.bg_inner_color_uniform, bg_inner_color);
+            uniform_color(self.bg_outer_color_uniform, bg_outer_color);
+            uniform_color(self.line_color_uniform, line_color);
         });
     }
 }
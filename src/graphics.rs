@@ -1,5 +1,6 @@
 use crate::KeyboardStatus;
-use crate::config::{CompletionOverlayConfig, RingOverlayConfig};
+use crate::config::{CompletionTheme, RingTheme};
+use crate::flat_keyboard::{FlatKey, FlatKeyboard, key_rects};
 use crate::font_rendering::{Font, FontAtlas, FontMetrics, FontRenderer, Layout, TextArranger};
 use crate::gl_primitives::{BaseBackgroundRenderer, CircleRenderer, RectangleRenderer};
 use crate::input_method::CleKeyButton;
@@ -254,6 +255,22 @@ pub fn draw_cursor_circle(context: &GraphicsContext, stick: Vec2, color: ColorF)
     );
 }
 
+/// Renders the stick positions traced for swipe typing, fading out the older ones.
+pub fn draw_swipe_trail(context: &GraphicsContext, trail: &[Vec2], color: ColorF) {
+    for (i, point) in trail.iter().enumerate() {
+        let ratio = (i + 1) as f32 / trail.len() as f32;
+        let color = ColorF::new(color.r(), color.g(), color.b(), color.a() * ratio);
+        context.circle_renderer.draw(
+            Transform2F::from_scale_rotation_translation(
+                Vector2F::splat(0.08),
+                0.0,
+                Vector2F::new(point.x, point.y),
+            ),
+            color,
+        );
+    }
+}
+
 fn calc_offsets(size: f32) -> [Vector2F; 8] {
     let axis = 0.75 * size;
     let diagonal = axis * FRAC_1_SQRT_2;
@@ -316,7 +333,8 @@ fn render_ring_chars(context: &mut GraphicsContext, center: Vector2F, ring: &Rin
 #[allow(clippy::too_many_arguments)]
 pub(crate) fn draw_ring<'a, const ALWAYS_SHOW_IN_CIRCLE: bool>(
     context: &mut GraphicsContext,
-    config: &RingOverlayConfig,
+    theme: &RingTheme,
+    accent_color: ColorF,
     button_idx: usize,
     current: i8,
     opposite: i8,
@@ -329,16 +347,17 @@ pub(crate) fn draw_ring<'a, const ALWAYS_SHOW_IN_CIRCLE: bool>(
 
     context.base_background_renderer.draw(
         Transform2F::default(),
-        config.center_color,
-        config.background_color,
-        config.edge_color,
+        theme.center_color,
+        theme.background.inner,
+        theme.background.outer,
+        accent_color,
     );
 
     if ALWAYS_SHOW_IN_CIRCLE || opposite == -1 {
         let default_color = if current == -1 {
-            config.normal_char_color
+            theme.normal_char_color
         } else {
-            config.un_selecting_char_color
+            theme.un_selecting_char_color
         };
 
         // initialize with general case.
@@ -356,11 +375,11 @@ pub(crate) fn draw_ring<'a, const ALWAYS_SHOW_IN_CIRCLE: bool>(
 
         if current == -1 {
             //if current == -1 and opposite is selected
-            //  prove[*].chars[opposite].color = config.selecting_char_in_ring_color;
+            //  prove[*].chars[opposite].color = theme.selecting_char_in_ring_color;
             if opposite != -1 {
                 let opposite = opposite as usize;
                 for ring in prove.iter_mut() {
-                    ring.chars[opposite].color = config.selecting_char_in_ring_color;
+                    ring.chars[opposite].color = theme.selecting_char_in_ring_color;
                 }
             }
         } else {
@@ -370,7 +389,7 @@ pub(crate) fn draw_ring<'a, const ALWAYS_SHOW_IN_CIRCLE: bool>(
             let ring = &mut prove[current];
             ring.ring_size = 0.22 * radius;
             for char in ring.chars.iter_mut() {
-                char.color = config.selecting_char_color;
+                char.color = theme.selecting_char_color;
             }
 
             // for selecting char, set color to selecting_char_in_ring_color
@@ -381,7 +400,7 @@ pub(crate) fn draw_ring<'a, const ALWAYS_SHOW_IN_CIRCLE: bool>(
                     let key = get_key(current, opposite);
                     key.0.get(button_idx).map(|x| x.shows).unwrap_or("")
                 };
-                char.color = config.selecting_char_in_ring_color;
+                char.color = theme.selecting_char_in_ring_color;
                 char.size = 1.2;
             }
         }
@@ -392,9 +411,9 @@ pub(crate) fn draw_ring<'a, const ALWAYS_SHOW_IN_CIRCLE: bool>(
         }
     } else {
         let default_color = if current == -1 {
-            config.normal_char_color
+            theme.normal_char_color
         } else {
-            config.un_selecting_char_color
+            theme.un_selecting_char_color
         };
 
         let mut ring = RingInfo {
@@ -410,18 +429,23 @@ pub(crate) fn draw_ring<'a, const ALWAYS_SHOW_IN_CIRCLE: bool>(
         };
 
         if current != -1 {
-            ring.chars[current as usize].color = config.selecting_char_color;
+            ring.chars[current as usize].color = theme.selecting_char_color;
             ring.chars[current as usize].size = 1.1;
         }
         render_ring_chars(context, Vector2F::zero(), &ring)
     }
 
-    draw_cursor_circle(context, stick_pos, ColorF::new(0.22, 0.22, 0.22, 1.0));
+    draw_cursor_circle(context, stick_pos, theme.cursor_color);
 }
 
+/// Lanes of the buffer fitting in the center field.
+pub const CENTER_LANES: usize = 5;
+
+/// Renders the buffer or the candidates, with up to `max_lanes` lines of the buffer.
 pub fn draw_center(
     status: &KeyboardStatus,
-    config: &CompletionOverlayConfig,
+    theme: &CompletionTheme,
+    max_lanes: usize,
     context: &mut GraphicsContext,
 ) {
     crate::gl_primitives::gl_clear(ColorF::transparent_black());
@@ -434,42 +458,82 @@ pub fn draw_center(
     let font_size = lane_height * FONT_SIZE_RATIO;
     let font_size = vec2f(font_size * 0.5, font_size);
 
-    context.rectangle_renderer.draw(
-        RectF::new(vec2f(-1.0, 1.0), vec2f(width, -lane_height)),
-        0.,
-        config.background_color,
-    );
-
     let max_width = 2.0 - 2.0 * space_x;
 
     if status.candidates.is_empty() {
         let metrics = context.font_layout.metrics();
-        let mut layout = context.font_layout.layout(&status.buffer, &[]);
-        let color = config.inputting_char_color;
+        let color = theme.inputting_char_color;
 
-        let mut cursor = vec2f(-1. + space_x, 1. - lane_height);
-        cursor.0[1] += lane_height / 2.0;
-        cursor.0[1] -= metrics.cap_height * font_size.y() / 2.0;
+        // each line of the buffer has its own lane, and the lanes with the caret are shown
+        let before_caret = &status.buffer[..status.cursor];
+        let caret_line = before_caret.matches('\n').count();
+        let caret_line_start = before_caret.rfind('\n').map_or(0, |x| x + 1);
+        let lines = status.buffer.split('\n').collect::<Vec<_>>();
+        let first = (caret_line + 1).saturating_sub(max_lanes);
+        let shown = &lines[first..lines.len().min(first + max_lanes)];
 
-        let length = layout.cursor_advance().x() * font_size.x() + font_size.x();
-        if length > max_width {
-            cursor.0[0] += max_width - length;
-        }
+        context.rectangle_renderer.draw(
+            RectF::new(
+                vec2f(-1.0, 1.0),
+                vec2f(width, -lane_height * shown.len() as f32),
+            ),
+            0.,
+            theme.background_color,
+        );
 
-        layout.apply_transform(Transform2F {
-            matrix: Matrix2x2F::from_scale(font_size),
-            vector: cursor,
-        });
+        for (i, line) in shown.iter().enumerate() {
+            let top = 1.0 - lane_height * i as f32;
+            let mut layout = context.font_layout.layout(line, &[]);
+
+            let mut cursor = vec2f(-1. + space_x, top - lane_height);
+            cursor.0[1] += lane_height / 2.0;
+            cursor.0[1] -= metrics.cap_height * font_size.y() / 2.0;
+
+            let length = layout.cursor_advance().x() * font_size.x() + font_size.x();
+            let caret = (first + i == caret_line).then(|| {
+                context
+                    .font_layout
+                    .layout(&before_caret[caret_line_start..], &[])
+                    .cursor_advance()
+                    .x()
+                    * font_size.x()
+            });
+            if length > max_width {
+                // the end is shown unless the caret would be out on the left
+                cursor.0[0] += (max_width - length).max(-caret.unwrap_or(length));
+            }
 
-        context.render_text(color, &layout);
-        draw_underline(
-            &context.rectangle_renderer,
-            font_size,
-            &layout,
-            metrics,
-            color,
-        );
+            layout.apply_transform(Transform2F {
+                matrix: Matrix2x2F::from_scale(font_size),
+                vector: cursor,
+            });
+
+            context.render_text(color, &layout);
+            draw_underline(
+                &context.rectangle_renderer,
+                font_size,
+                &layout,
+                metrics,
+                color,
+            );
+            if let Some(caret) = caret {
+                context.rectangle_renderer.draw(
+                    RectF::new(
+                        vec2f(cursor.x() + caret, top - lane_height * 0.85),
+                        vec2f(font_size.x() * 0.08, lane_height * 0.7),
+                    ),
+                    0.0,
+                    color,
+                );
+            }
+        }
     } else {
+        context.rectangle_renderer.draw(
+            RectF::new(vec2f(-1.0, 1.0), vec2f(width, -lane_height)),
+            0.,
+            theme.background_color,
+        );
+
         let metrics = context.font_layout.metrics();
         let mut advance = 0.0;
         let layouts = status
@@ -503,9 +567,9 @@ pub fn draw_center(
 
         for (i, (_, mut layout)) in layouts.into_iter().enumerate() {
             let color = if i == status.candidates_idx {
-                config.inputting_char_color
+                theme.inputting_char_color
             } else {
-                ColorF::black()
+                theme.candidate_char_color
             };
 
             layout.apply_transform(Transform2F {
@@ -573,11 +637,11 @@ pub fn draw_center(
                 vec2f(width, -lane_height * candidates.len() as f32),
             ),
             0.0,
-            config.background_color,
+            theme.background_color,
         );
 
         for layout in layouts.iter() {
-            context.render_text(config.inputting_char_color, layout);
+            context.render_text(theme.inputting_char_color, layout);
         }
 
         for candidate in &status.candidates {
@@ -609,3 +673,70 @@ fn draw_underline(
         rectangle_renderer.draw(underline, 0.0, color);
     }
 }
+
+/// Renders lines of text on the center field, used for messages other than buffer.
+pub fn draw_message(text: &str, theme: &CompletionTheme, context: &mut GraphicsContext) {
+    crate::gl_primitives::gl_clear(ColorF::transparent_black());
+    const SPACE_RATIO: f32 = 0.1;
+    const FONT_SIZE_RATIO: f32 = 0.7;
+
+    let width = 2.0;
+    let lane_height = 0.36;
+    let space_x = lane_height * SPACE_RATIO * 0.5;
+    let font_size = lane_height * FONT_SIZE_RATIO;
+    let font_size = vec2f(font_size * 0.5, font_size);
+
+    let metrics = context.font_layout.metrics();
+    for (i, line) in text.lines().enumerate() {
+        let top = 1.0 - lane_height * i as f32;
+        context.rectangle_renderer.draw(
+            RectF::new(vec2f(-1.0, top), vec2f(width, -lane_height)),
+            0.,
+            theme.background_color,
+        );
+
+        let mut layout = context.font_layout.layout(line, &[]);
+
+        let mut cursor = vec2f(-1. + space_x, top - lane_height);
+        cursor.0[1] += lane_height / 2.0;
+        cursor.0[1] -= metrics.cap_height * font_size.y() / 2.0;
+
+        layout.apply_transform(Transform2F {
+            matrix: Matrix2x2F::from_scale(font_size),
+            vector: cursor,
+        });
+
+        context.render_text(theme.inputting_char_color, &layout);
+    }
+}
+
+/// Renders the keys of the flat keyboard below the buffer lane.
+pub fn draw_flat_keyboard(
+    context: &mut GraphicsContext,
+    theme: &CompletionTheme,
+    accent_color: ColorF,
+    keyboard: &FlatKeyboard,
+) {
+    const LABEL_RATIO: f32 = 0.6;
+
+    for (key, origin, size) in key_rects() {
+        let rect = RectF::new(vec2f(origin.x, origin.y), vec2f(size.x, size.y));
+        let active = keyboard.hovered == Some(key) || (key == FlatKey::Shift && keyboard.shift);
+        let background = if active {
+            accent_color
+        } else {
+            theme.background_color
+        };
+        context.rectangle_renderer.draw(rect, 0., background);
+
+        let label = key.label(keyboard.shift);
+        let box_size = vec2f(size.x, size.y) * LABEL_RATIO;
+        render_text_in_box(
+            context,
+            box_size,
+            &label,
+            theme.inputting_char_color,
+            rect.center(),
+        );
+    }
+}
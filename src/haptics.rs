@@ -0,0 +1,64 @@
+//! Haptic feedback for keyboard events.
+
+use crate::config::{HapticPulse, HapticsConfig};
+use crate::{Application, LeftRight};
+
+#[derive(Copy, Clone)]
+pub(crate) enum HapticEvent {
+    SectorChange,
+    MultiTapStep,
+    // a character or text entered on the keyboard
+    Character,
+    // text sent to the application
+    Commit,
+    Backspace,
+    PlaneSwitch,
+    ConversionFailure,
+    FlushFailure,
+}
+
+impl HapticsConfig {
+    fn pulse(&self, event: HapticEvent) -> &HapticPulse {
+        match event {
+            HapticEvent::SectorChange => &self.sector_change,
+            HapticEvent::MultiTapStep => &self.multi_tap_step,
+            HapticEvent::Character => &self.character,
+            HapticEvent::Commit => &self.commit,
+            HapticEvent::Backspace => &self.backspace,
+            HapticEvent::PlaneSwitch => &self.plane_switch,
+            HapticEvent::ConversionFailure => &self.conversion_failure,
+            HapticEvent::FlushFailure => &self.flush_failure,
+        }
+    }
+}
+
+impl<'a> Application<'a> {
+    /// Plays the pulse for the event on the hand, or on both hands if None.
+    pub(crate) fn play_haptic(&self, event: HapticEvent, hand: Option<LeftRight>) {
+        let config = &self.config.haptics;
+        if !config.enabled {
+            return;
+        }
+        let pulse = config.pulse(event);
+        let amplitude = (pulse.amplitude * config.intensity).clamp(0.0, 1.0);
+        if amplitude <= 0.0 {
+            return;
+        }
+
+        let hands = [LeftRight::Left, LeftRight::Right]
+            .into_iter()
+            .filter(|x| hand.is_none_or(|hand| hand == *x));
+        for hand in hands {
+            for i in 0..pulse.count {
+                let start = i as f32 * pulse.duration * 2.0;
+                self.ovr_controller.play_haptics(
+                    hand,
+                    start,
+                    pulse.duration,
+                    pulse.frequency,
+                    amplitude,
+                );
+            }
+        }
+    }
+}
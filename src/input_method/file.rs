@@ -0,0 +1,213 @@
+//! Loading input tables from appdata to replace the builtin planes.
+//!
+//! The builtin planes are also written to `tables/builtin` as a base to edit by hand
+//! or to give to the layout optimizer.
+
+use super::*;
+use crate::global::get_appdata_dir;
+use crate::table_file::{ACTION_PREFIX, CELLS, RESERVED_CELLS, TableFile};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+// special actions with the name in table files
+struct ExtraAction {
+    name: &'static str,
+    shows: &'static str,
+    action: fn(&mut KeyboardStatus),
+}
+
+static EXTRA_ACTIONS: [ExtraAction; 3] = [
+    ExtraAction {
+        name: "small",
+        shows: "小",
+        action: jp_small,
+    },
+    ExtraAction {
+        name: "dakuten",
+        shows: "\u{2B1A}\u{3099}",
+        action: jp_dakuten,
+    },
+    ExtraAction {
+        name: "handakuten",
+        shows: "\u{2B1A}\u{309a}",
+        action: jp_handakuten,
+    },
+];
+
+fn get_tables_dir() -> PathBuf {
+    get_appdata_dir().join("tables")
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Returns the table in appdata with the name of the builtin one, or the builtin one.
+pub(crate) fn load_table(
+    builtin: &'static CleKeyInputTable<'static>,
+) -> &'static CleKeyInputTable<'static> {
+    if let Err(err) = write_builtin(builtin) {
+        log::warn!("writing builtin table {}: {err}", builtin.name);
+    }
+
+    let path = get_tables_dir().join(format!("{}.json", builtin.name));
+    match read_table(&path, builtin.name) {
+        Ok(table) => {
+            log::info!("using table {}", path.display());
+            // tables live as long as the app like the builtin ones
+            Box::leak(Box::new(table))
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => builtin,
+        Err(err) => {
+            log::error!("loading table {}: {err}", path.display());
+            builtin
+        }
+    }
+}
+
+fn read_table(path: &Path, name: &'static str) -> io::Result<CleKeyInputTable<'static>> {
+    parse_table(serde_json::from_reader(File::open(path)?)?, name)
+}
+
+fn parse_table(file: TableFile, name: &'static str) -> io::Result<CleKeyInputTable<'static>> {
+    if file.name != name {
+        return Err(invalid(format!(
+            "table for {} instead of {name}",
+            file.name
+        )));
+    }
+    if file.table.len() != CELLS {
+        return Err(invalid(format!(
+            "{} cells instead of {CELLS}",
+            file.table.len()
+        )));
+    }
+
+    let mut table = [CleKeyButton::empty(); CELLS];
+    for (idx, cell) in file.table.iter().enumerate() {
+        if RESERVED_CELLS.contains(&idx) {
+            if !cell.is_empty() {
+                log::warn!("{name}: cell {idx} is for builtin keys and ignored");
+            }
+            continue;
+        }
+        let actions = cell
+            .iter()
+            .map(|x| parse_action(x))
+            .collect::<io::Result<Vec<_>>>()?;
+        table[idx] = CleKeyButton(Box::leak(actions.into_boxed_slice()));
+    }
+
+    Ok(CleKeyInputTable {
+        name,
+        starts_ime: file.starts_ime,
+        table,
+    })
+}
+
+fn parse_action(entry: &str) -> io::Result<CleKeyButtonAction<'static>> {
+    // a lone prefix is the character itself
+    if let Some(action) = entry.strip_prefix(ACTION_PREFIX)
+        && !action.is_empty()
+    {
+        let extra = EXTRA_ACTIONS
+            .iter()
+            .find(|x| x.name == action)
+            .ok_or_else(|| invalid(format!("unknown action {entry}")))?;
+        return Ok(CleKeyButtonAction {
+            shows: extra.shows,
+            action: InputNextAction::Extra(extra.action),
+        });
+    }
+
+    let mut chars = entry.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(CleKeyButtonAction {
+            shows: Box::leak(entry.to_owned().into_boxed_str()),
+            action: InputNextAction::EnterChar(c),
+        }),
+        _ => Err(invalid(format!("{entry:?} is not a character"))),
+    }
+}
+
+fn action_name(action: &CleKeyButtonAction) -> Option<String> {
+    match action.action {
+        InputNextAction::EnterChar(c) => Some(c.to_string()),
+        InputNextAction::Extra(_) => EXTRA_ACTIONS
+            .iter()
+            .find(|x| x.shows == action.shows)
+            .map(|x| format!("{ACTION_PREFIX}{}", x.name)),
+        InputNextAction::EnterText(_)
+        | InputNextAction::EnterSnippet(_)
+        | InputNextAction::Intrinsic(_) => None,
+    }
+}
+
+fn write_builtin(table: &CleKeyInputTable) -> io::Result<()> {
+    let file = table_file(table);
+    let path = get_tables_dir()
+        .join("builtin")
+        .join(format!("{}.json", table.name));
+    fs::create_dir_all(path.parent().unwrap())?;
+    serde_json::to_writer_pretty(File::create(path)?, &file)?;
+    Ok(())
+}
+
+fn table_file(table: &CleKeyInputTable) -> TableFile {
+    TableFile {
+        name: table.name.to_owned(),
+        starts_ime: table.starts_ime,
+        table: table
+            .table
+            .iter()
+            .enumerate()
+            .map(|(idx, button)| {
+                if RESERVED_CELLS.contains(&idx) {
+                    vec![]
+                } else {
+                    button.0.iter().filter_map(action_name).collect()
+                }
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lone_prefix_is_a_character() {
+        let action = parse_action("#").unwrap();
+        assert!(matches!(
+            action.action,
+            InputNextAction::EnterChar(ACTION_PREFIX)
+        ));
+        assert_eq!(action.shows, "#");
+    }
+
+    fn names(button: &CleKeyButton) -> Vec<(Option<String>, String)> {
+        let actions = button.0.iter();
+        actions
+            .map(|x| (action_name(x), x.shows.to_owned()))
+            .collect()
+    }
+
+    #[test]
+    fn builtin_tables_round_trip() {
+        for builtin in [SIGNS_TABLE, ENGLISH_TABLE, JAPANESE_INPUT] {
+            let json = serde_json::to_string(&table_file(builtin)).unwrap();
+            let file = serde_json::from_str(&json).unwrap();
+            let table = parse_table(file, builtin.name).unwrap();
+
+            assert_eq!(table.starts_ime, builtin.starts_ime);
+            for (idx, (read, written)) in table.table.iter().zip(&builtin.table).enumerate() {
+                if RESERVED_CELLS.contains(&idx) {
+                    continue;
+                }
+                assert_eq!(names(read), names(written), "{} cell {idx}", builtin.name);
+            }
+        }
+    }
+}
@@ -1,22 +1,47 @@
 use crate::{Application, KeyboardStatus};
 
+mod file;
+pub(crate) use file::load_table;
+
 #[derive(Copy, Clone, Debug)]
 pub enum HardKeyButton {
     CloseButton,
+    Backspace,
+    Space,
+    Enter,
+    NextPlane,
+    SignPlane,
+    StartConversion,
+    Commit,
 }
 
 impl HardKeyButton {
-    pub const VALUES: [HardKeyButton; 1] = [HardKeyButton::CloseButton];
+    pub const VALUES: [HardKeyButton; 8] = [
+        HardKeyButton::CloseButton,
+        HardKeyButton::Backspace,
+        HardKeyButton::Space,
+        HardKeyButton::Enter,
+        HardKeyButton::NextPlane,
+        HardKeyButton::SignPlane,
+        HardKeyButton::StartConversion,
+        HardKeyButton::Commit,
+    ];
 }
 
 pub(crate) enum InputNextAction {
     EnterChar(char),
+    // a whole text like a past one from the clipboard history
+    EnterText(&'static str),
+    // a snippet with the placeholders to fill in
+    EnterSnippet(&'static str),
     Extra(fn(&mut KeyboardStatus)),
     Intrinsic(fn(&mut Application)),
 }
 
 #[derive(Clone)]
 pub(crate) struct CleKeyInputTable<'a> {
+    // the name of the plane, used to look up theme accents
+    pub name: &'a str,
     pub starts_ime: bool,
     pub table: [CleKeyButton<'a>; 8 * 8],
 }
@@ -65,11 +90,21 @@ macro_rules! single_extra_action {
     };
 }
 
+macro_rules! builtin_button {
+    ($char: literal = $func: expr) => {
+        CleKeyButton(&[CleKeyButtonAction {
+            shows: $char,
+            action: InputNextAction::Intrinsic($func),
+        }])
+    };
+}
+
+// replaces the character before the cursor
 macro_rules! replace_last_char {
     ($vis: vis fn $name: ident { $($tt:tt)* }) => {
         $vis fn $name(status: &mut KeyboardStatus) {
-            if let Some(c) = status.buffer.pop() {
-                status.buffer.push({
+            if let Some(c) = status.remove_char() {
+                status.insert_char({
                     static MAPPING: [char; 6 * 16] = {
                         let mut init = ['\0'; 6 * 16];
                         replace_last_char!(@first_init_0 init; 0, 1, 2, 3, 4, 5);
@@ -111,6 +146,7 @@ macro_rules! replace_last_char {
 }
 
 pub(crate) static SIGNS_TABLE: &CleKeyInputTable = &CleKeyInputTable {
+    name: "signs",
     starts_ime: false,
     table: [
         char_button!('('),
@@ -181,6 +217,7 @@ pub(crate) static SIGNS_TABLE: &CleKeyInputTable = &CleKeyInputTable {
 };
 
 pub(crate) static ENGLISH_TABLE: &CleKeyInputTable = &CleKeyInputTable {
+    name: "english",
     starts_ime: false,
     table: [
         char_button!('a'),
@@ -251,6 +288,7 @@ pub(crate) static ENGLISH_TABLE: &CleKeyInputTable = &CleKeyInputTable {
 };
 
 pub(crate) static JAPANESE_INPUT: &CleKeyInputTable = &CleKeyInputTable {
+    name: "japanese",
     starts_ime: true,
     table: [
         char_button!('あ', 'ぁ'),
@@ -2,21 +2,39 @@
 
 #[macro_use]
 mod utils;
+mod calibration;
+mod clipboard_history;
 mod config;
 #[cfg(feature = "debug_window")]
 mod debug_graphics;
+mod flat_keyboard;
 mod font_rendering;
 mod gl_primitives;
 mod global;
 mod graphics;
+mod haptics;
+#[macro_use]
 mod input_method;
 mod licenses;
+mod one_hand;
 mod os;
+mod output_rules;
 mod ovr_controller;
+mod placement;
 mod resources;
-
-use crate::config::{CleKeyConfig, UIMode, load_config};
+mod settings;
+mod snippets;
+mod stats;
+mod swipe;
+mod table_file;
+mod undo;
+
+use crate::config::{
+    CleKeyConfig, RingTheme, SelectionMode, StickConfig, Theme, UIMode, load_config, load_theme,
+    write_config,
+};
 use crate::graphics::GraphicsContext;
+use crate::haptics::HapticEvent;
 use crate::input_method::{CleKeyButton, CleKeyInputTable, HardKeyButton, InputNextAction};
 use crate::ovr_controller::{ActionSetKind, ButtonKind, OVRController, OverlayPlane};
 use crate::utils::GlContextExt;
@@ -153,6 +171,8 @@ fn main() {
 
     load_config(&mut config);
 
+    let theme = load_theme(&config.theme);
+
     let ovr_controller = OVRController::new(&global::get_resources_dir()).expect("ovr controller");
     ovr_controller
         .load_config(&config)
@@ -160,7 +180,8 @@ fn main() {
 
     let mut app = Application::new(
         &ovr_controller,
-        &config,
+        config,
+        theme,
         if cfg!(feature = "openvr") {
             Rc::new(Waiting)
         } else {
@@ -183,7 +204,7 @@ fn main() {
 
     // fps throttling
     let frame_duration = {
-        let actual_fps = config.fps.max(1.0);
+        let actual_fps = app.config.fps.max(1.0);
         let frame_dur_nano = Duration::new(1, 0).as_nanos() as f64 / actual_fps as f64;
         Duration::new(0, frame_dur_nano as u32)
     };
@@ -192,14 +213,25 @@ fn main() {
         let frame_end_expected = Instant::now() + frame_duration;
 
         #[allow(deprecated)]
-        winit::platform::pump_events::EventLoopExtPumpEvents::pump_events(
+        let status = winit::platform::pump_events::EventLoopExtPumpEvents::pump_events(
             &mut event_loop,
             Some(Duration::ZERO),
-            |_e, _active| {
+            |e, active| {
+                if let winit::event::Event::WindowEvent {
+                    event: winit::event::WindowEvent::CloseRequested,
+                    ..
+                } = e
+                {
+                    active.exit();
+                }
                 #[cfg(feature = "debug_control")]
-                ovr_controller.accept_debug_control(_e)
+                ovr_controller.accept_debug_control(e)
             },
         );
+        if let winit::platform::pump_events::PumpStatus::Exit(_) = status {
+            app.stats.end_session();
+            break;
+        }
 
         graphics_context.receive_atlas();
 
@@ -261,41 +293,46 @@ struct Application<'a> {
     kbd_status: KeyboardStatus,
     click_started: Instant,
     app_status: Rc<dyn ApplicationStatus>,
-    config: &'a CleKeyConfig,
+    config: CleKeyConfig,
+    theme: Theme,
     surfaces: Surfaces,
+    flat_keyboard: flat_keyboard::FlatKeyboard,
+    one_hand: one_hand::OneHandStep,
+    swipe: swipe::Swipe,
+    stats: stats::Stats,
+    history: undo::History,
+    clipboard: clipboard_history::ClipboardHistory,
+    snippet_plane: snippets::SnippetPlane,
 }
 
 impl<'a> Application<'a> {
     pub fn new(
         ovr: &'a OVRController,
-        config: &'a CleKeyConfig,
+        config: CleKeyConfig,
+        theme: Theme,
         app_status: Rc<dyn ApplicationStatus>,
         surfaces: Surfaces,
     ) -> Self {
         use input_method::*;
+        let clipboard = clipboard_history::ClipboardHistory::load(&config);
         let mut result = Self {
             ovr_controller: ovr,
-            sign_input: SIGNS_TABLE,
-            methods: VecDeque::from([JAPANESE_INPUT, ENGLISH_TABLE]),
+            sign_input: load_table(SIGNS_TABLE),
+            methods: VecDeque::from([load_table(JAPANESE_INPUT), load_table(ENGLISH_TABLE)]),
             is_sign: false,
-            kbd_status: KeyboardStatus {
-                left: HandInfo::new(),
-                right: HandInfo::new(),
-                method: CleKeyInputTable {
-                    starts_ime: false,
-                    table: [CleKeyButton::empty(); 8 * 8],
-                },
-                button_idx: 0,
-                buffer: String::new(),
-                closing: false,
-                candidates: vec![],
-                candidates_idx: 0,
-                henkan_using: None,
-            },
+            kbd_status: KeyboardStatus::new(),
             click_started: Instant::now(),
             app_status,
             config,
+            theme,
             surfaces,
+            flat_keyboard: Default::default(),
+            one_hand: Default::default(),
+            swipe: Default::default(),
+            stats: Default::default(),
+            history: Default::default(),
+            clipboard,
+            snippet_plane: Default::default(),
         };
 
         result.set_plane(result.methods.front().unwrap());
@@ -310,21 +347,90 @@ impl<'a> Application<'a> {
                 self.surfaces.right_ring.renderer = renderer_fn::right_ring_renderer;
                 self.surfaces.center_field.renderer = renderer_fn::center_field_renderer;
             }
-            UIMode::OneRing => {
+            UIMode::OneRing | UIMode::OneHand => {
                 self.surfaces.left_ring.renderer = renderer_fn::one_ring_renderer;
                 self.surfaces.right_ring.renderer = renderer_fn::nop_renderer;
                 self.surfaces.center_field.renderer = renderer_fn::center_field_renderer;
             }
+            UIMode::Flat => {
+                self.surfaces.left_ring.renderer = renderer_fn::one_ring_renderer;
+                self.surfaces.right_ring.renderer = renderer_fn::nop_renderer;
+                self.surfaces.center_field.renderer = renderer_fn::flat_keyboard_renderer;
+            }
+        }
+    }
+
+    pub(crate) fn set_settings_renderers(&mut self) {
+        self.surfaces.center_field.renderer = renderer_fn::settings_renderer;
+    }
+
+    /// Saves the config and applies it to the overlays and renderers.
+    pub(crate) fn save_config(&self) {
+        if let Err(err) = write_config(&self.config) {
+            log::error!("saving config: {err}");
         }
     }
 
+    /// Applies the config to the overlays without saving it.
+    pub(crate) fn apply_config(&mut self) {
+        if let Err(err) = self.ovr_controller.load_config(&self.config) {
+            log::error!("loading config on ovr: {err:?}");
+        }
+        self.set_default_renderers();
+    }
+
+    pub(crate) fn reload_theme(&mut self) {
+        self.theme = load_theme(&self.config.theme);
+        self.set_default_renderers();
+    }
+
+    pub(crate) fn open_settings(&mut self) {
+        self.kbd_status.method.clone_from(settings::SETTINGS_TABLE);
+        self.set_default_renderers();
+        self.set_settings_renderers();
+        self.app_status = Rc::new(Settings);
+    }
+
+    pub(crate) fn open_placement(&mut self) {
+        self.surfaces.center_field.renderer = renderer_fn::placement_renderer;
+        self.app_status = Rc::new(placement::Placing::default());
+    }
+
+    pub(crate) fn open_calibration(&mut self) {
+        self.set_calibration_renderer(true);
+        self.app_status = Rc::new(calibration::Calibrating::new());
+    }
+
+    pub(crate) fn set_stats_renderer(&mut self) {
+        self.surfaces.center_field.renderer = renderer_fn::stats_renderer;
+    }
+
+    pub(crate) fn set_calibration_renderer(&mut self, resting: bool) {
+        self.surfaces.center_field.renderer = if resting {
+            renderer_fn::calibration_rest_renderer
+        } else {
+            renderer_fn::calibration_renderer
+        };
+    }
+
+    pub(crate) fn close_settings(&mut self) {
+        self.save_config();
+        self.set_current_plane();
+        self.set_default_renderers();
+        self.app_status = Rc::new(Inputting);
+    }
+
+    pub(crate) fn accent_color(&self) -> pathfinder_color::ColorF {
+        self.theme.accent_color(self.kbd_status.method.name)
+    }
+
     pub(crate) fn set_henkan_renderers(&mut self) {
         match self.config.ui_mode {
             UIMode::TwoRing => {
                 self.surfaces.left_ring.renderer = renderer_fn::left_ring_henkan_renderer;
                 self.surfaces.right_ring.renderer = renderer_fn::right_ring_henkan_renderer;
             }
-            UIMode::OneRing => {
+            UIMode::OneRing | UIMode::Flat | UIMode::OneHand => {
                 self.surfaces.left_ring.renderer = renderer_fn::one_ring_henkan_renderer;
                 self.surfaces.right_ring.renderer = renderer_fn::nop_renderer;
             }
@@ -352,6 +458,14 @@ impl ApplicationStatus for Waiting {
         app.ovr_controller.hide_all_overlay();
 
         if app.ovr_controller.click_started(HardKeyButton::CloseButton) {
+            // world-locked overlays are placed where the head is now,
+            // and controllers may be connected after startup
+            if let Err(err) = app.ovr_controller.load_config(&app.config) {
+                log::error!("loading config on ovr: {err:?}");
+            }
+            app.ovr_controller.reload_calibration();
+            // a new session starts when the keyboard is opened
+            app.stats.start_session();
             app.app_status = Rc::new(Inputting);
         }
     }
@@ -366,33 +480,81 @@ impl ApplicationStatus for Inputting {
             ActionSetKind::Input,
             ActionSetKind::Waiting,
         ]);
-        app.ovr_controller.update_status(&mut app.kbd_status);
+        app.update_kbd_status();
 
-        match app.config.ui_mode {
-            UIMode::TwoRing => {
-                app.ovr_controller.show_overlay(OverlayPlane::Left);
-                app.ovr_controller.show_overlay(OverlayPlane::Right);
-            }
-            UIMode::OneRing => {
+        if let UIMode::Flat = app.config.ui_mode {
+            // the keys are on the center and the ring is only for conversion
+            app.ovr_controller.show_overlay(OverlayPlane::Center);
+            app.ovr_controller.hide_overlay(OverlayPlane::Right);
+            if app.kbd_status.candidates.is_empty() {
+                app.ovr_controller.hide_overlay(OverlayPlane::Left);
+            } else {
                 app.ovr_controller.show_overlay(OverlayPlane::Left);
             }
-        }
-        if !app.kbd_status.buffer.is_empty() {
-            app.ovr_controller.show_overlay(OverlayPlane::Center);
         } else {
-            app.ovr_controller.hide_overlay(OverlayPlane::Center);
+            show_ring_overlays(app);
+            if !app.kbd_status.buffer.is_empty() {
+                app.ovr_controller.show_overlay(OverlayPlane::Center);
+            } else {
+                app.ovr_controller.hide_overlay(OverlayPlane::Center);
+            }
         }
 
-        if app.kbd_tick() {
+        let flat_keys =
+            matches!(app.config.ui_mode, UIMode::Flat) && app.kbd_status.candidates.is_empty();
+        let closing = if flat_keys {
+            app.flat_keyboard_tick()
+        } else {
+            app.kbd_tick()
+        };
+        if closing || app.hard_key_tick() {
+            app.stats.end_session();
+            app.history.clear();
             app.app_status = Rc::new(Waiting);
         }
 
         if app.ovr_controller.button_status(ButtonKind::SuspendInput) {
+            // the keyboard is hidden; resuming starts another session
+            app.stats.end_session();
             app.app_status = Rc::new(Suspending)
         }
     }
 }
 
+fn show_ring_overlays(app: &Application) {
+    match app.config.ui_mode {
+        UIMode::TwoRing => {
+            app.ovr_controller.show_overlay(OverlayPlane::Left);
+            app.ovr_controller.show_overlay(OverlayPlane::Right);
+        }
+        UIMode::OneRing | UIMode::Flat | UIMode::OneHand => {
+            app.ovr_controller.show_overlay(OverlayPlane::Left);
+            app.ovr_controller.hide_overlay(OverlayPlane::Right);
+        }
+    }
+}
+
+struct Settings;
+
+impl ApplicationStatus for Settings {
+    fn tick(&self, app: &mut Application) {
+        app.ovr_controller.set_active_action_set([
+            ActionSetKind::Suspender,
+            ActionSetKind::Input,
+            ActionSetKind::Waiting,
+        ]);
+        app.update_kbd_status();
+
+        show_ring_overlays(app);
+        app.ovr_controller.show_overlay(OverlayPlane::Center);
+
+        // close button goes back to the keyboard instead of closing it
+        if app.kbd_tick() || app.ovr_controller.click_started(HardKeyButton::CloseButton) {
+            app.close_settings();
+        }
+    }
+}
+
 struct Suspending;
 
 impl ApplicationStatus for Suspending {
@@ -430,39 +592,49 @@ impl SurfaceInfo {
 
 mod renderer_fn {
     use super::*;
-    use crate::config;
-    use crate::graphics::{draw_center, draw_ring};
+    use crate::graphics::{
+        CENTER_LANES, draw_center, draw_flat_keyboard, draw_message, draw_ring, draw_swipe_trail,
+    };
 
     pub(crate) fn nop_renderer(_: &mut GraphicsContext, _: &Application) {}
 
     pub(crate) fn one_ring_renderer(context: &mut GraphicsContext, app: &Application) {
         draw_ring::<true>(
             context,
-            &app.config.one_ring.ring,
+            &app.theme.ring,
+            app.accent_color(),
             app.kbd_status.button_idx,
             app.kbd_status.left.selection,
             app.kbd_status.right.selection,
             app.kbd_status.left.stick,
             |current, opposite| app.kbd_status.method.table[8 * current + opposite],
         );
+        if let Some(path) = &app.swipe.path {
+            draw_swipe_trail(context, &path.trail, app.theme.ring.cursor_color);
+        }
     }
 
     pub(crate) fn left_ring_renderer(context: &mut GraphicsContext, app: &Application) {
         draw_ring::<true>(
             context,
-            &app.config.two_ring.left_ring,
+            &app.theme.left_ring,
+            app.accent_color(),
             app.kbd_status.button_idx,
             app.kbd_status.left.selection,
             app.kbd_status.right.selection,
             app.kbd_status.left.stick,
             |current, opposite| app.kbd_status.method.table[8 * current + opposite],
         );
+        if let Some(path) = &app.swipe.path {
+            draw_swipe_trail(context, &path.trail, app.theme.left_ring.cursor_color);
+        }
     }
 
     pub(crate) fn right_ring_renderer(context: &mut GraphicsContext, app: &Application) {
         draw_ring::<false>(
             context,
-            &app.config.two_ring.right_ring,
+            &app.theme.right_ring,
+            app.accent_color(),
             app.kbd_status.button_idx,
             app.kbd_status.right.selection,
             app.kbd_status.left.selection,
@@ -472,19 +644,75 @@ mod renderer_fn {
     }
 
     pub(crate) fn center_field_renderer(context: &mut GraphicsContext, app: &Application) {
-        draw_center(&app.kbd_status, &app.config.two_ring.completion, context);
+        draw_center(
+            &app.kbd_status,
+            &app.theme.completion,
+            CENTER_LANES,
+            context,
+        );
+    }
+
+    pub(crate) fn flat_keyboard_renderer(context: &mut GraphicsContext, app: &Application) {
+        // the keys are below the first lane
+        draw_center(&app.kbd_status, &app.theme.completion, 1, context);
+        // candidates are listed below the buffer while converting
+        if app.kbd_status.candidates.is_empty() {
+            draw_flat_keyboard(
+                context,
+                &app.theme.completion,
+                app.accent_color(),
+                &app.flat_keyboard,
+            );
+        }
+    }
+
+    pub(crate) fn placement_renderer(context: &mut GraphicsContext, app: &Application) {
+        draw_message(
+            "Trigger: move, Stick: resize, Stick click: done",
+            &app.theme.completion,
+            context,
+        );
+    }
+
+    pub(crate) fn calibration_rest_renderer(context: &mut GraphicsContext, app: &Application) {
+        draw_message(
+            "Keep your thumbs off the sticks",
+            &app.theme.completion,
+            context,
+        );
+    }
+
+    pub(crate) fn calibration_renderer(context: &mut GraphicsContext, app: &Application) {
+        draw_message(
+            "Rotate both sticks along the edge, then click a stick",
+            &app.theme.completion,
+            context,
+        );
+    }
+
+    pub(crate) fn stats_renderer(context: &mut GraphicsContext, app: &Application) {
+        draw_message(&app.stats.summary, &app.theme.completion, context);
+    }
+
+    pub(crate) fn settings_renderer(context: &mut GraphicsContext, app: &Application) {
+        draw_message(
+            &settings::describe(app, app.kbd_status.left.selection),
+            &app.theme.completion,
+            context,
+        );
     }
 
     pub(crate) fn henkan_renderer_impl(
         context: &mut GraphicsContext,
-        config0: &CleKeyConfig,
-        config: &config::RingOverlayConfig,
+        app: &Application,
+        theme: &RingTheme,
         hand: &HandInfo,
     ) {
-        if config0.always_enter_paste {
+        if app.config.always_enter_paste {
             draw_ring::<false>(
                 context,
-                config,
+                theme,
+                app.accent_color(),
                 0,
                 hand.selection,
                 1,
@@ -494,7 +722,8 @@ mod renderer_fn {
         } else {
             draw_ring::<false>(
                 context,
-                config,
+                theme,
+                app.accent_color(),
                 0,
                 hand.selection,
                 1,
@@ -505,48 +734,23 @@ mod renderer_fn {
     }
 
     pub(crate) fn left_ring_henkan_renderer(context: &mut GraphicsContext, app: &Application) {
-        henkan_renderer_impl(
-            context,
-            app.config,
-            &app.config.two_ring.left_ring,
-            &app.kbd_status.left,
-        );
+        henkan_renderer_impl(context, app, &app.theme.left_ring, &app.kbd_status.left);
     }
 
     pub(crate) fn right_ring_henkan_renderer(context: &mut GraphicsContext, app: &Application) {
-        henkan_renderer_impl(
-            context,
-            app.config,
-            &app.config.two_ring.right_ring,
-            &app.kbd_status.right,
-        );
+        henkan_renderer_impl(context, app, &app.theme.right_ring, &app.kbd_status.right);
     }
 
     pub(crate) fn one_ring_henkan_renderer(context: &mut GraphicsContext, app: &Application) {
         match app.kbd_status.henkan_using {
             None => {
-                henkan_renderer_impl(
-                    context,
-                    app.config,
-                    &app.config.one_ring.ring,
-                    &app.kbd_status.left,
-                );
+                henkan_renderer_impl(context, app, &app.theme.ring, &app.kbd_status.left);
             }
             Some(LeftRight::Left) => {
-                henkan_renderer_impl(
-                    context,
-                    app.config,
-                    &app.config.one_ring.ring,
-                    &app.kbd_status.left,
-                );
+                henkan_renderer_impl(context, app, &app.theme.ring, &app.kbd_status.left);
             }
             Some(LeftRight::Right) => {
-                henkan_renderer_impl(
-                    context,
-                    app.config,
-                    &app.config.one_ring.ring,
-                    &app.kbd_status.right,
-                );
+                henkan_renderer_impl(context, app, &app.theme.ring, &app.kbd_status.right);
             }
         }
     }
@@ -603,6 +807,8 @@ pub struct HandInfo {
 
     clicking: bool,
     clicking_old: bool,
+    // how deep the trigger is pulled
+    pull: f32,
 }
 
 impl HandInfo {
@@ -613,6 +819,11 @@ impl HandInfo {
     pub(crate) fn click_started(&self) -> bool {
         !self.clicking_old && self.clicking
     }
+
+    pub(crate) fn stick_touched(&self, config: &StickConfig) -> bool {
+        // remaining drift after calibration should not count as touching
+        self.stick.length_squared() > config.dead_zone * config.dead_zone
+    }
 }
 
 impl HandInfo {
@@ -624,6 +835,7 @@ impl HandInfo {
             selection_old: -1,
             clicking: false,
             clicking_old: false,
+            pull: 0.0,
         }
     }
 }
@@ -634,18 +846,85 @@ pub struct KeyboardStatus {
     method: CleKeyInputTable<'static>,
     button_idx: usize,
     buffer: String,
+    // in bytes, on a character boundary of the buffer
+    cursor: usize,
     closing: bool,
     candidates: Vec<HenkanCandidate>,
     candidates_idx: usize,
     henkan_using: Option<LeftRight>,
+    // the button being tapped in tap count selection mode
+    tapping: Option<CleKeyButton<'static>>,
 }
 
+#[derive(Clone)]
 pub struct HenkanCandidate {
     candidates: Vec<String>,
     index: usize,
 }
 
 impl KeyboardStatus {
+    fn new() -> Self {
+        Self {
+            left: HandInfo::new(),
+            right: HandInfo::new(),
+            method: CleKeyInputTable {
+                name: "",
+                starts_ime: false,
+                table: [CleKeyButton::empty(); 8 * 8],
+            },
+            button_idx: 0,
+            buffer: String::new(),
+            cursor: 0,
+            closing: false,
+            candidates: vec![],
+            candidates_idx: 0,
+            henkan_using: None,
+            tapping: None,
+        }
+    }
+
+    /// Inserts the character at the cursor and moves the cursor after it.
+    pub(crate) fn insert_char(&mut self, c: char) {
+        self.buffer.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+    }
+
+    /// Inserts the text at the cursor and moves the cursor after it.
+    pub(crate) fn insert_str(&mut self, text: &str) {
+        self.buffer.insert_str(self.cursor, text);
+        self.cursor += text.len();
+    }
+
+    /// Removes the character before the cursor.
+    pub(crate) fn remove_char(&mut self) -> Option<char> {
+        let c = self.buffer[..self.cursor].chars().next_back()?;
+        self.cursor -= c.len_utf8();
+        self.buffer.remove(self.cursor);
+        Some(c)
+    }
+
+    pub(crate) fn move_cursor_left(&mut self) {
+        if let Some(c) = self.buffer[..self.cursor].chars().next_back() {
+            self.cursor -= c.len_utf8();
+        }
+    }
+
+    pub(crate) fn move_cursor_right(&mut self) {
+        if let Some(c) = self.buffer[self.cursor..].chars().next() {
+            self.cursor += c.len_utf8();
+        }
+    }
+
+    /// Whether a backspace would go to the field with its caret at the start.
+    pub(crate) fn at_field_start(&self) -> bool {
+        self.buffer.is_empty() && os::focused_text().is_some_and(|x| x.caret == 0)
+    }
+
+    pub(crate) fn clear_buffer(&mut self) {
+        self.buffer.clear();
+        self.cursor = 0;
+    }
+
     pub(crate) fn is_selecting(&self) -> bool {
         self.left.selection != -1 && self.right.selection != -1
     }
@@ -672,6 +951,10 @@ impl KeyboardStatus {
         self.left.clicking || self.right.clicking
     }
 
+    pub(crate) fn pull(&self) -> f32 {
+        self.left.pull.max(self.right.pull)
+    }
+
     pub(crate) fn selecting_button(&self) -> Option<CleKeyButton<'static>> {
         if self.is_selecting() {
             Some(self.method.table[(self.left.selection * 8 + self.right.selection) as usize])
@@ -682,6 +965,28 @@ impl KeyboardStatus {
 }
 
 impl<'a> Application<'a> {
+    pub(crate) fn update_kbd_status(&mut self) {
+        // candidates are chosen with the sticks as they are
+        let one_hand =
+            matches!(self.config.ui_mode, UIMode::OneHand) && self.kbd_status.candidates.is_empty();
+        let (left, right) = if one_hand {
+            self.update_one_hand_status();
+            let hand = self.config.one_hand.stick.hand();
+            (hand, hand)
+        } else {
+            self.reset_one_hand_status();
+            self.ovr_controller
+                .update_status(&mut self.kbd_status, &self.config.stick);
+            (LeftRight::Left, LeftRight::Right)
+        };
+        if self.kbd_status.left.selection_changed() {
+            self.play_haptic(HapticEvent::SectorChange, Some(left));
+        }
+        if self.kbd_status.right.selection_changed() {
+            self.play_haptic(HapticEvent::SectorChange, Some(right));
+        }
+    }
+
     pub(crate) fn kbd_tick(&mut self) -> bool {
         if self.kbd_status.candidates.is_empty() {
             self.kbd_inputting_tick()
@@ -691,19 +996,75 @@ impl<'a> Application<'a> {
     }
 
     pub(crate) fn kbd_inputting_tick(&mut self) -> bool {
+        match self.config.click.selection {
+            SelectionMode::TapCount => self.kbd_tap_count_tick(),
+            SelectionMode::Swipe if self.swipe.has_dictionary(self.kbd_status.method.name) => {
+                self.kbd_swipe_tick()
+            }
+            // planes without a dictionary are typed as with the time selection
+            SelectionMode::Swipe | SelectionMode::Time | SelectionMode::Pull => {
+                self.kbd_holding_tick()
+            }
+        }
+    }
+
+    /// Handles the buttons bound to keys; returns true if the keyboard should be closed.
+    pub(crate) fn hard_key_tick(&mut self) -> bool {
+        if !self.kbd_status.candidates.is_empty() {
+            // while converting, only committing is available
+            if self.ovr_controller.click_started(HardKeyButton::Commit) && self.flush(false) {
+                self.set_default_renderers();
+            }
+            return false;
+        }
+
+        for x in HardKeyButton::VALUES {
+            if self.ovr_controller.click_started(x) {
+                match x {
+                    HardKeyButton::CloseButton => return true,
+                    HardKeyButton::Backspace => Self::backspace_key(self),
+                    HardKeyButton::Space => Self::space_key(self),
+                    HardKeyButton::Enter => Self::enter_key(self),
+                    HardKeyButton::NextPlane => Self::next_plane_key(self),
+                    HardKeyButton::SignPlane => Self::sign_plane_key(self),
+                    HardKeyButton::StartConversion => {
+                        if !self.kbd_status.buffer.is_empty() {
+                            Self::henkan_key(self)
+                        }
+                    }
+                    HardKeyButton::Commit => Self::commit_key(self),
+                }
+            }
+        }
+        false
+    }
+
+    // the character is chosen while holding the trigger and input on release
+    fn kbd_holding_tick(&mut self) -> bool {
         if let Some(button) = self.kbd_status.selecting_button() {
             if self.kbd_status.click_started() || self.kbd_status.selection_changed() {
                 self.click_started = Instant::now();
                 self.kbd_status.button_idx = 0
             } else if self.kbd_status.clicking() {
-                if !button.0.is_empty() {
+                let button_idx = self.kbd_status.button_idx;
+                if button.0.is_empty() {
+                    self.kbd_status.button_idx = 0;
+                } else if self.config.click.selection == SelectionMode::Pull {
+                    // keep the deepest one so releasing the trigger does not go back
+                    let pulled = (self.kbd_status.pull() * button.0.len() as f32) as usize;
+                    self.kbd_status.button_idx = self
+                        .kbd_status
+                        .button_idx
+                        .max(pulled.min(button.0.len() - 1));
+                } else {
                     let dur = Instant::now().duration_since(self.click_started);
                     let millis = dur.as_millis();
                     self.kbd_status.button_idx =
                         (((millis + self.config.click.offset) / self.config.click.length)
                             % button.0.len() as u128) as usize;
-                } else {
-                    self.kbd_status.button_idx = 0;
+                }
+                if self.kbd_status.button_idx != button_idx {
+                    self.play_haptic(HapticEvent::MultiTapStep, None);
                 }
             } else if self.kbd_status.click_stopped() {
                 info!(
@@ -721,16 +1082,49 @@ impl<'a> Application<'a> {
                 }
             }
         }
+        false
+    }
 
-        for x in HardKeyButton::VALUES {
-            if self.ovr_controller.click_started(x) {
-                match x {
-                    HardKeyButton::CloseButton => return true,
-                    #[allow(unreachable_patterns)]
-                    _ => {
-                        todo!()
-                        //let action = self.status.method.on_hard_input(x);
-                        //self.do_input_action(action)
+    // each press chooses the next character and the character is input
+    // when the tap window passes or another button is selected
+    fn kbd_tap_count_tick(&mut self) -> bool {
+        if let Some(button) = self.kbd_status.tapping {
+            let expired = !self.kbd_status.clicking()
+                && Instant::now()
+                    .duration_since(self.click_started)
+                    .as_millis()
+                    >= self.config.click.tap_window;
+            if expired || self.kbd_status.selection_changed() {
+                self.kbd_status.tapping = None;
+                if let Some(action) = button.0.get(self.kbd_status.button_idx).map(|x| &x.action) {
+                    self.do_input_action(action)
+                }
+                self.kbd_status.button_idx = 0;
+                if take(&mut self.kbd_status.closing) {
+                    return true;
+                }
+            }
+        }
+
+        if let Some(button) = self.kbd_status.selecting_button() {
+            if self.kbd_status.click_started() && !button.0.is_empty() {
+                if self.kbd_status.tapping.is_some() {
+                    self.kbd_status.button_idx = (self.kbd_status.button_idx + 1) % button.0.len();
+                    self.play_haptic(HapticEvent::MultiTapStep, None);
+                } else {
+                    self.kbd_status.button_idx = 0;
+                    self.kbd_status.tapping = Some(button);
+                }
+            } else if self.kbd_status.click_stopped() && self.kbd_status.tapping.is_some() {
+                // the tap window starts on release
+                self.click_started = Instant::now();
+                if button.0.len() == 1 {
+                    // nothing to cycle; do not wait for the window
+                    self.kbd_status.tapping = None;
+                    self.do_input_action(&button.0[0].action);
+                    self.kbd_status.button_idx = 0;
+                    if take(&mut self.kbd_status.closing) {
+                        return true;
                     }
                 }
             }
@@ -760,12 +1154,12 @@ impl<'a> Application<'a> {
             None
         }
         fn action_left(app: &mut Application) {
-            if let Some(action) = get_input_action(app.config, &app.kbd_status.left) {
+            if let Some(action) = get_input_action(&app.config, &app.kbd_status.left) {
                 app.do_input_action(action);
             }
         }
         fn action_right(app: &mut Application) {
-            if let Some(action) = get_input_action(app.config, &app.kbd_status.right) {
+            if let Some(action) = get_input_action(&app.config, &app.kbd_status.right) {
                 app.do_input_action(action);
             }
         }
@@ -774,27 +1168,29 @@ impl<'a> Application<'a> {
                 action_left(self);
                 action_right(self);
             }
-            UIMode::OneRing => match self.kbd_status.henkan_using {
-                None => {
-                    if self.kbd_status.left.stick != Vec2::ZERO {
-                        self.kbd_status.henkan_using = Some(LeftRight::Left);
-                    } else if self.kbd_status.right.stick != Vec2::ZERO {
-                        self.kbd_status.henkan_using = Some(LeftRight::Right);
+            UIMode::OneRing | UIMode::Flat | UIMode::OneHand => {
+                match self.kbd_status.henkan_using {
+                    None => {
+                        if self.kbd_status.left.stick_touched(&self.config.stick) {
+                            self.kbd_status.henkan_using = Some(LeftRight::Left);
+                        } else if self.kbd_status.right.stick_touched(&self.config.stick) {
+                            self.kbd_status.henkan_using = Some(LeftRight::Right);
+                        }
                     }
-                }
-                Some(LeftRight::Left) => {
-                    action_left(self);
-                    if self.kbd_status.left.stick == Vec2::ZERO {
-                        self.kbd_status.henkan_using = None
+                    Some(LeftRight::Left) => {
+                        action_left(self);
+                        if !self.kbd_status.left.stick_touched(&self.config.stick) {
+                            self.kbd_status.henkan_using = None
+                        }
                     }
-                }
-                Some(LeftRight::Right) => {
-                    action_right(self);
-                    if self.kbd_status.right.stick == Vec2::ZERO {
-                        self.kbd_status.henkan_using = None
+                    Some(LeftRight::Right) => {
+                        action_right(self);
+                        if !self.kbd_status.right.stick_touched(&self.config.stick) {
+                            self.kbd_status.henkan_using = None
+                        }
                     }
                 }
-            },
+            }
         }
 
         for x in HardKeyButton::VALUES {
@@ -814,22 +1210,66 @@ impl<'a> Application<'a> {
     fn do_input_action(&mut self, action: &InputNextAction) {
         match action {
             InputNextAction::EnterChar(c) => {
+                self.play_haptic(HapticEvent::Character, None);
+                self.stats.character();
                 if self.config.always_use_buffer
                     || self.kbd_status.method.starts_ime
                     || !self.kbd_status.buffer.is_empty()
                 {
-                    self.kbd_status.buffer.push(*c);
+                    self.record_edit();
+                    self.kbd_status.insert_char(*c);
                     self.set_inputting_table();
                 } else {
-                    os::enter_char(*c)
+                    os::enter_char(*c);
+                    self.stats.commit(c.encode_utf8(&mut [0; 4]), []);
                 }
             }
-            InputNextAction::Extra(f) => f(&mut self.kbd_status),
+            InputNextAction::EnterText(text) => self.enter_text(text),
+            InputNextAction::EnterSnippet(text) => {
+                self.enter_text(&snippets::fill_placeholders(text))
+            }
+            InputNextAction::Extra(f) => {
+                self.record_edit();
+                f(&mut self.kbd_status)
+            }
             InputNextAction::Intrinsic(f) => f(self),
         }
     }
 
+    fn enter_text(&mut self, text: &str) {
+        if self.config.always_use_buffer || !self.kbd_status.buffer.is_empty() {
+            self.play_haptic(HapticEvent::Character, None);
+            self.record_edit();
+            self.kbd_status.insert_str(text);
+            self.set_inputting_table();
+        } else if self.send_text(text, true) {
+            self.play_haptic(HapticEvent::Commit, None);
+            self.stats.commit(text, []);
+            self.clipboard.add(text, &self.config);
+        } else {
+            self.play_haptic(HapticEvent::FlushFailure, None);
+        }
+        // the planes of texts are left once one is chosen
+        if matches!(
+            self.kbd_status.method.name,
+            clipboard_history::CLIPBOARD_PLANE | snippets::SNIPPETS_PLANE
+        ) {
+            self.set_current_plane();
+        }
+    }
+
+    /// Sets the sign plane or the current input method, leaving the planes of texts.
+    pub(crate) fn set_current_plane(&mut self) {
+        if self.is_sign {
+            self.set_plane(self.sign_input);
+        } else {
+            self.set_plane(self.methods.front().unwrap());
+        }
+    }
+
     fn move_to_next_plane(&mut self) {
+        self.record_edit();
+        self.play_haptic(HapticEvent::PlaneSwitch, None);
         self.is_sign = false;
         // rotate
         self.methods.rotate_left(1);
@@ -838,6 +1278,8 @@ impl<'a> Application<'a> {
     }
 
     fn swap_sign_plane(&mut self) {
+        self.record_edit();
+        self.play_haptic(HapticEvent::PlaneSwitch, None);
         if self.is_sign {
             self.is_sign = false;
             self.set_plane(self.methods.front().unwrap());
@@ -850,7 +1292,13 @@ impl<'a> Application<'a> {
     pub fn flush(&mut self, force_paste: bool) -> bool {
         let mut builder = String::new();
         let buffer = if self.kbd_status.candidates.is_empty() {
-            self.kbd_status.buffer.as_str()
+            match snippets::expand_abbreviation(&self.kbd_status.buffer, &self.config.snippets) {
+                Some(expanded) => {
+                    builder = expanded;
+                    &builder
+                }
+                None => self.kbd_status.buffer.as_str(),
+            }
         } else {
             for x in &self.kbd_status.candidates {
                 builder.push_str(&x.candidates[x.index]);
@@ -859,16 +1307,20 @@ impl<'a> Application<'a> {
         };
         let mut success = true;
         if !buffer.is_empty() {
-            let enter = force_paste || self.config.always_enter_paste;
-            if enter {
-                success = os::enter_text(buffer)
-            } else {
-                success = os::copy_text(buffer);
-            }
+            success = self.send_text(buffer, force_paste);
+        }
+        if !success {
+            self.play_haptic(HapticEvent::FlushFailure, None);
+        } else if !buffer.is_empty() {
+            self.play_haptic(HapticEvent::Commit, None);
         }
         if success {
+            let indices = self.kbd_status.candidates.iter().map(|x| x.index);
+            self.stats.commit(buffer, indices);
+            self.clipboard.add(buffer, &self.config);
+            self.history.clear();
             self.set_inputted_table();
-            self.kbd_status.buffer.clear();
+            self.kbd_status.clear_buffer();
             self.kbd_status.candidates.clear();
         }
         success
@@ -929,6 +1381,7 @@ impl<'a> Application<'a> {
                     vec![mgr.kbd_status.buffer.clone()],
                 )];
             }
+            mgr.record_edit();
             mgr.kbd_status.candidates_idx = 0;
             mgr.kbd_status.henkan_using = None;
             mgr.kbd_status.candidates = response
@@ -938,8 +1391,11 @@ impl<'a> Application<'a> {
                     index: 0,
                 })
                 .collect();
+            mgr.stats.conversion();
             mgr.set_henkan_renderers();
-        };
+        } else {
+            mgr.play_haptic(HapticEvent::ConversionFailure, None);
+        }
     }
 
     fn add_candidates(original: String, mut vec: Vec<String>) -> Vec<String> {
@@ -952,29 +1408,74 @@ impl<'a> Application<'a> {
         vec
     }
 
+    fn settings_key(mgr: &mut Application) {
+        mgr.open_settings();
+    }
+
+    // a line break is kept in the buffer while inputting, and sent as enter otherwise
     fn new_line_key(mgr: &mut Application) {
-        debug_assert!(mgr.kbd_status.buffer.is_empty());
-        os::enter_enter();
+        if mgr.kbd_status.buffer.is_empty() {
+            os::enter_enter();
+        } else {
+            mgr.record_edit();
+            mgr.kbd_status.insert_char('\n');
+        }
+    }
+
+    fn enter_key(mgr: &mut Application) {
+        if mgr.kbd_status.buffer.is_empty() {
+            Self::new_line_key(mgr)
+        } else if mgr.flush(true) {
+            mgr.set_default_renderers();
+        }
+    }
+
+    fn commit_key(mgr: &mut Application) {
+        if !mgr.kbd_status.buffer.is_empty() && mgr.flush(false) {
+            mgr.set_default_renderers();
+        }
     }
 
     fn backspace_key(mgr: &mut Application) {
-        if mgr.kbd_status.buffer.pop().is_some() {
+        mgr.play_haptic(HapticEvent::Backspace, None);
+        // counted only when something is deleted
+        if !mgr.kbd_status.buffer.is_empty() {
+            if mgr.kbd_status.cursor > 0 {
+                mgr.stats.backspace();
+                mgr.record_edit();
+                mgr.kbd_status.remove_char();
+            }
             if mgr.kbd_status.buffer.is_empty() {
                 mgr.set_inputted_table();
             }
+        } else if mgr.kbd_status.at_field_start() {
+            // nothing to delete in the field
+            mgr.play_haptic(HapticEvent::FlushFailure, None);
         } else {
+            mgr.stats.backspace();
             os::enter_backspace();
         }
     }
 
     fn space_key(mgr: &mut Application) {
+        mgr.stats.character();
         if mgr.kbd_status.buffer.is_empty() {
             os::enter_char(' ');
+            mgr.stats.commit(" ", []);
         } else {
-            mgr.kbd_status.buffer.push(' ');
+            mgr.record_edit();
+            mgr.kbd_status.insert_char(' ');
         }
     }
 
+    fn cursor_left_key(mgr: &mut Application) {
+        mgr.kbd_status.move_cursor_left();
+    }
+
+    fn cursor_right_key(mgr: &mut Application) {
+        mgr.kbd_status.move_cursor_right();
+    }
+
     fn next_plane_key(mgr: &mut Application) {
         mgr.move_to_next_plane()
     }
@@ -982,15 +1483,14 @@ impl<'a> Application<'a> {
     fn sign_plane_key(mgr: &mut Application) {
         mgr.swap_sign_plane()
     }
-}
 
-macro_rules! builtin_button {
-    ($char: literal = $func: expr) => {
-        CleKeyButton(&[CleKeyButtonAction {
-            shows: $char,
-            action: InputNextAction::Intrinsic($func),
-        }])
-    };
+    fn clipboard_plane_key(mgr: &mut Application) {
+        mgr.swap_clipboard_plane()
+    }
+
+    fn snippets_plane_key(mgr: &mut Application) {
+        mgr.swap_snippets_plane()
+    }
 }
 
 impl<'ovr> Application<'ovr> {
@@ -999,13 +1499,39 @@ impl<'ovr> Application<'ovr> {
         self.kbd_status.method.clone_from(table);
 
         use Application as App;
-        self.kbd_status.method.table[6 * 8 + 6] = builtin_button!("⌫" = App::backspace_key);
-        self.kbd_status.method.table[6 * 8 + 7] = builtin_button!("␣" = App::space_key);
+        // hold backspace or space to move the cursor
+        self.kbd_status.method.table[6 * 8 + 6] = CleKeyButton(&[
+            CleKeyButtonAction {
+                shows: "⌫",
+                action: InputNextAction::Intrinsic(App::backspace_key),
+            },
+            CleKeyButtonAction {
+                shows: "←",
+                action: InputNextAction::Intrinsic(App::cursor_left_key),
+            },
+        ]);
+        self.kbd_status.method.table[6 * 8 + 7] = CleKeyButton(&[
+            CleKeyButtonAction {
+                shows: "␣",
+                action: InputNextAction::Intrinsic(App::space_key),
+            },
+            CleKeyButtonAction {
+                shows: "→",
+                action: InputNextAction::Intrinsic(App::cursor_right_key),
+            },
+        ]);
 
-        // 🌐
-        self.kbd_status.method.table[7 * 8 + 6] =
-            builtin_button!("\u{1F310}" = App::next_plane_key);
-        self.kbd_status.method.table[7 * 8 + 7] = builtin_button!("#+=" = App::sign_plane_key);
+        // hold the sign plane key for the clipboard history
+        self.kbd_status.method.table[7 * 8 + 7] = CleKeyButton(&[
+            CleKeyButtonAction {
+                shows: "#+=",
+                action: InputNextAction::Intrinsic(App::sign_plane_key),
+            },
+            CleKeyButtonAction {
+                shows: "\u{1F4CB}",
+                action: InputNextAction::Intrinsic(App::clipboard_plane_key),
+            },
+        ]);
 
         if self.kbd_status.buffer.is_empty() {
             self.set_inputted_table();
@@ -1017,36 +1543,61 @@ impl<'ovr> Application<'ovr> {
     fn set_inputted_table(&mut self) {
         use input_method::*;
         self.kbd_status.method.table[5 * 8 + 6] = builtin_button!("Close" = Application::close_key);
-        self.kbd_status.method.table[5 * 8 + 7] = builtin_button!("⏎" = Application::new_line_key);
+        self.kbd_status.method.table[5 * 8 + 7] = undo::ENTER_UNDO_BUTTON;
+        // the settings are opened only while nothing is being inputted,
+        // so the gear is held past the snippets on the next plane key
+        self.kbd_status.method.table[7 * 8 + 6] = CleKeyButton(&[
+            NEXT_PLANE_ACTION,
+            SNIPPETS_PLANE_ACTION,
+            CleKeyButtonAction {
+                shows: "\u{2699}",
+                action: InputNextAction::Intrinsic(Application::settings_key),
+            },
+        ]);
     }
 
     fn set_inputting_table(&mut self) {
         use input_method::*;
         self.kbd_status.method.table[5 * 8 + 6] = builtin_button!("変換" = Application::henkan_key);
-        self.kbd_status.method.table[5 * 8 + 7] = CleKeyButton::empty();
+        self.kbd_status.method.table[5 * 8 + 7] = undo::ENTER_UNDO_BUTTON;
+        self.kbd_status.method.table[7 * 8 + 6] =
+            CleKeyButton(&[NEXT_PLANE_ACTION, SNIPPETS_PLANE_ACTION]);
     }
 }
 
+// 🌐
+// hold the next plane key for the snippets
+const NEXT_PLANE_ACTION: input_method::CleKeyButtonAction = input_method::CleKeyButtonAction {
+    shows: "\u{1F310}",
+    action: InputNextAction::Intrinsic(Application::next_plane_key),
+};
+const SNIPPETS_PLANE_ACTION: input_method::CleKeyButtonAction = input_method::CleKeyButtonAction {
+    shows: "\u{1F4DD}",
+    action: InputNextAction::Intrinsic(Application::snippets_plane_key),
+};
+
 mod ime_specific {
     use crate::Application;
     use crate::input_method::{CleKeyButton, CleKeyButtonAction, InputNextAction};
+    use crate::undo::{REDO_BUTTON, UNDO_BUTTON};
 
     pub(crate) static BUTTONS: [CleKeyButton; 8] = [
         builtin_button!("↑" = up_key),
         builtin_button!("Cancel" = cancel_key),
         builtin_button!("→" = right_key),
-        CleKeyButton::empty(),
+        UNDO_BUTTON,
         builtin_button!("↓" = down_key),
-        CleKeyButton::empty(),
+        REDO_BUTTON,
         builtin_button!("←" = left_key),
         builtin_button!("確定" = kakutei_key),
     ];
 
+    // no cell is left for redo; it is on the enter key once the conversion is undone
     pub(crate) static BUTTONS_PASTE_OPTIONAL: [CleKeyButton; 8] = [
         builtin_button!("↑" = up_key),
         builtin_button!("Cancel" = cancel_key),
         builtin_button!("→" = right_key),
-        CleKeyButton::empty(),
+        UNDO_BUTTON,
         builtin_button!("↓" = down_key),
         builtin_button!("Copy" = kakutei_key),
         builtin_button!("←" = left_key),
@@ -1054,6 +1605,7 @@ mod ime_specific {
     ];
 
     fn cancel_key(mgr: &mut Application) {
+        mgr.record_edit();
         mgr.kbd_status.candidates.clear();
         mgr.kbd_status.candidates_idx = 0;
         mgr.set_default_renderers();
@@ -1104,3 +1656,22 @@ mod ime_specific {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::os::{focused_field as field, with_focused_text};
+
+    #[test]
+    fn backspace_at_field_start() {
+        let mut status = KeyboardStatus::new();
+        assert!(with_focused_text(field("hello", 0), || status.at_field_start()));
+        assert!(!with_focused_text(field("hello", 5), || status.at_field_start()));
+        // the field is not known; the backspace is sent
+        assert!(!with_focused_text(None, || status.at_field_start()));
+
+        // the buffer is edited first
+        status.insert_char('a');
+        assert!(!with_focused_text(field("hello", 0), || status.at_field_start()));
+    }
+}
@@ -0,0 +1,116 @@
+//! One hand mode: the row and the column of the table are chosen with two flicks of one stick.
+//!
+//! The first flick, tilting and releasing the stick, chooses the row. The next tilt chooses the
+//! column and the trigger inputs the character as usual. The stick is translated to the left (row)
+//! and right (column) selections so the rest of the keyboard works as in the one ring mode.
+//! The row is dropped when the stick is released after clicking or rests for the step timeout.
+
+use crate::config::StickSide;
+use crate::{Application, HandInfo, LeftRight};
+use std::mem::take;
+use std::time::Instant;
+
+pub(crate) struct OneHandStep {
+    // the stick as it is
+    stick: HandInfo,
+    row: Option<i8>,
+    // when the row was chosen or the column was released without clicking
+    since: Instant,
+    // clicked with a column selected; releasing the stick starts over
+    clicked: bool,
+    // updated since the last reset
+    active: bool,
+}
+
+impl Default for OneHandStep {
+    fn default() -> Self {
+        Self {
+            stick: HandInfo::new(),
+            row: None,
+            since: Instant::now(),
+            clicked: false,
+            active: false,
+        }
+    }
+}
+
+impl StickSide {
+    pub(crate) fn hand(self) -> LeftRight {
+        match self {
+            StickSide::Left => LeftRight::Left,
+            StickSide::Right => LeftRight::Right,
+        }
+    }
+}
+
+impl Application<'_> {
+    /// Drops the steps when the stick is read without the one hand mode.
+    pub(crate) fn reset_one_hand_status(&mut self) {
+        if self.one_hand.active {
+            self.one_hand = Default::default();
+        }
+    }
+
+    pub(crate) fn update_one_hand_status(&mut self) {
+        let clicking = self.kbd_status.clicking();
+        let step = &mut self.one_hand;
+        step.active = true;
+        self.ovr_controller.update_hand_status(
+            &mut step.stick,
+            self.config.one_hand.stick.hand(),
+            clicking,
+            &self.config.stick,
+        );
+
+        let stick = &step.stick;
+        let released = stick.selection_old != -1 && stick.selection == -1;
+        match step.row {
+            None => {
+                if released {
+                    step.row = Some(stick.selection_old);
+                    step.since = Instant::now();
+                }
+            }
+            Some(_) => {
+                if stick.clicking && stick.selection != -1 {
+                    step.clicked = true;
+                }
+                if released {
+                    if take(&mut step.clicked) {
+                        step.row = None;
+                    } else {
+                        // a wrong column; another one can be chosen
+                        step.since = Instant::now();
+                    }
+                } else if stick.selection == -1
+                    && Instant::now().duration_since(step.since).as_millis()
+                        >= self.config.one_hand.step_timeout
+                {
+                    step.row = None;
+                }
+            }
+        }
+
+        let stick = &step.stick;
+        let (row, column) = match step.row {
+            None => (stick.selection, -1),
+            Some(row) => (row, stick.selection),
+        };
+
+        let left = &mut self.kbd_status.left;
+        left.stick = stick.stick;
+        left.selection_old = left.selection;
+        left.selection = row;
+        left.clicking_old = stick.clicking_old;
+        left.clicking = stick.clicking;
+        left.pull = stick.pull;
+
+        let right = &mut self.kbd_status.right;
+        right.stick = stick.stick;
+        right.selection_old = right.selection;
+        right.selection = column;
+        right.clicking_old = false;
+        right.clicking = false;
+        right.pull = 0.0;
+    }
+}
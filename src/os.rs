@@ -1,4 +1,21 @@
 use cfg_if::cfg_if;
+use serde::Deserialize;
+
+/// The text of the focused text field in the target application, read back to edit with context.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SurroundingText {
+    pub text: String,
+    // in characters
+    pub caret: usize,
+}
+
+/// The focused window, to choose how text is sent to it.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct FocusedApp {
+    pub class: String,
+    // the file name of the executable
+    pub process: String,
+}
 
 macro_rules! import {
     ($name: ident) => {
@@ -6,10 +23,18 @@ macro_rules! import {
         pub use $name::*;
     };
 }
+mod clipboard_paste;
+mod keymap;
+
 cfg_if! {
     if #[cfg(windows)] {
         import!(win);
     } else {
+        // with linux_desktop, the mock reads and writes the desktop but the tests don't
+        #[cfg(all(target_os = "linux", feature = "linux_desktop", not(test)))]
+        mod linux;
+        #[cfg(all(target_os = "linux", feature = "linux_desktop", not(test)))]
+        mod x11;
         import!(mock);
     }
 }
@@ -0,0 +1,278 @@
+//! Pasting through the clipboard while keeping what the user had copied.
+//!
+//! The previous contents are saved, the text is set and pasted with Ctrl+V, and the previous
+//! contents are put back once the application has read the clipboard. Opening the clipboard
+//! is retried while another process holds it, and the holder is logged to find who keeps it.
+//! Only text is put back; if something else was copied, the pasted text is left instead of
+//! clearing it.
+//!
+//! The retries and the wait take a while, so the texts are pasted one by one on a thread of
+//! their own instead of the frame loop. The frame loop still waits until Ctrl+V is sent, for
+//! a while, so that a failure is known and later keys arrive after the paste.
+
+use log::*;
+use std::io;
+use std::sync::mpsc::{Sender, SyncSender, channel, sync_channel};
+use std::thread::sleep;
+use std::time::Duration;
+
+const RETRIES: u32 = 10;
+const RETRY_INTERVAL: Duration = Duration::from_millis(50);
+const READ_TIMEOUT: Duration = Duration::from_millis(500);
+// longer than the retries, and than the wait of the paste before
+const PASTE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// What the clipboard holds.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Contents {
+    Empty,
+    Text(String),
+    // images, files and so on; the mock clipboard holds only text
+    #[cfg_attr(not(any(windows, feature = "linux_desktop")), allow(dead_code))]
+    Other,
+}
+
+/// The clipboard of a backend.
+pub(super) trait PasteClipboard {
+    fn read(&mut self) -> io::Result<Contents>;
+    /// Sets the text, or clears the clipboard with None.
+    fn write(&mut self, text: Option<&str>) -> io::Result<()>;
+    /// Describes the process holding the clipboard open.
+    fn lock_holder(&self) -> Option<String>;
+    fn send_paste(&mut self);
+    /// Waits until the application reads the clipboard; returns false on timeout.
+    fn wait_read(&mut self, timeout: Duration) -> bool;
+}
+
+fn retry<C: PasteClipboard, T>(
+    clipboard: &mut C,
+    doing: &str,
+    mut f: impl FnMut(&mut C) -> io::Result<T>,
+) -> io::Result<T> {
+    let mut failures = 0;
+    loop {
+        match f(clipboard) {
+            Ok(value) => return Ok(value),
+            Err(err) => {
+                let holder = clipboard.lock_holder();
+                let holder = holder.as_deref().unwrap_or("unknown");
+                if failures == RETRIES {
+                    error!("{doing}: {err}, held by {holder}");
+                    return Err(err);
+                }
+                info!("{doing} failure #{failures}: {err}, held by {holder}");
+                failures += 1;
+                sleep(RETRY_INTERVAL);
+            }
+        }
+    }
+}
+
+/// Sets the text and calls `paste` to send Ctrl+V, or to give up with false, and then puts
+/// back the previous contents.
+pub(super) fn paste_restoring<C: PasteClipboard>(
+    clipboard: &mut C,
+    text: &str,
+    paste: impl FnOnce(&mut C) -> bool,
+) -> bool {
+    let Ok(previous) = retry(clipboard, "reading clipboard", |x| x.read()) else {
+        return false;
+    };
+    if retry(clipboard, "setting clipboard", |x| x.write(Some(text))).is_err() {
+        return false;
+    }
+
+    let pasted = paste(clipboard);
+    if !pasted {
+        info!("the paste was given up");
+    } else if !clipboard.wait_read(READ_TIMEOUT) {
+        warn!("the clipboard was not read in {READ_TIMEOUT:?}");
+    }
+
+    let previous = match previous {
+        Contents::Empty => None,
+        Contents::Text(ref text) => Some(text.as_str()),
+        Contents::Other => {
+            info!("the clipboard held other than text; the pasted text is left");
+            return pasted;
+        }
+    };
+    // the text is sent even if the previous contents are lost
+    if retry(clipboard, "restoring clipboard", |x| x.write(previous)).is_err() {
+        error!("the previous clipboard contents are lost");
+    }
+    pasted
+}
+
+/// Pastes the texts in order on a thread of its own.
+pub(super) struct Paster {
+    // with where to tell that the clipboard is set, and then that Ctrl+V is sent
+    sender: Sender<(String, SyncSender<bool>)>,
+}
+
+impl Paster {
+    pub(super) fn new(mut clipboard: impl PasteClipboard + Send + 'static) -> Paster {
+        let (sender, receiver) = channel::<(String, SyncSender<bool>)>();
+        std::thread::Builder::new()
+            .name("clipboard paste".to_owned())
+            .spawn(move || {
+                for (text, reply) in receiver {
+                    let pasted = paste_restoring(&mut clipboard, &text, |clipboard| {
+                        // nobody waits if it took too long; pasting now would go after later keys
+                        if reply.send(true).is_err() {
+                            return false;
+                        }
+                        clipboard.send_paste();
+                        reply.send(true).is_ok()
+                    });
+                    // the failures before Ctrl+V; the others are already told
+                    if !pasted {
+                        reply.send(false).ok();
+                    }
+                }
+            })
+            .expect("starting clipboard paste thread");
+        Paster { sender }
+    }
+
+    /// Waits until Ctrl+V is sent; returns false if the clipboard could not be set in time.
+    pub(super) fn paste(&self, text: &str) -> bool {
+        // with no buffer, the paste thread gives up if the wait here has timed out
+        let (reply, replied) = sync_channel(0);
+        if self.sender.send((text.to_owned(), reply)).is_err() {
+            return false;
+        }
+        match replied.recv_timeout(PASTE_TIMEOUT) {
+            Ok(true) => replied.recv().unwrap_or(false),
+            Ok(false) => false,
+            Err(_) => {
+                error!("the clipboard was not set in {PASTE_TIMEOUT:?}");
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    // fails the given number of calls as held by the process
+    struct FakeClipboard {
+        contents: Contents,
+        failures: u32,
+        holder_asked: Cell<u32>,
+        writes: Vec<Option<String>>,
+        pasted: Option<Contents>,
+    }
+
+    impl FakeClipboard {
+        fn new(contents: Contents, failures: u32) -> FakeClipboard {
+            FakeClipboard {
+                contents,
+                failures,
+                holder_asked: Cell::new(0),
+                writes: vec![],
+                pasted: None,
+            }
+        }
+
+        fn open(&mut self) -> io::Result<()> {
+            if self.failures == 0 {
+                return Ok(());
+            }
+            self.failures -= 1;
+            Err(io::Error::new(io::ErrorKind::WouldBlock, "held"))
+        }
+    }
+
+    impl PasteClipboard for FakeClipboard {
+        fn read(&mut self) -> io::Result<Contents> {
+            self.open()?;
+            Ok(self.contents.clone())
+        }
+
+        fn write(&mut self, text: Option<&str>) -> io::Result<()> {
+            self.open()?;
+            self.writes.push(text.map(str::to_owned));
+            self.contents = text.map_or(Contents::Empty, |x| Contents::Text(x.to_owned()));
+            Ok(())
+        }
+
+        fn lock_holder(&self) -> Option<String> {
+            self.holder_asked.set(self.holder_asked.get() + 1);
+            Some("holder.exe".to_owned())
+        }
+
+        fn send_paste(&mut self) {
+            self.pasted = Some(self.contents.clone());
+        }
+
+        fn wait_read(&mut self, _: Duration) -> bool {
+            true
+        }
+    }
+
+    fn text(text: &str) -> Contents {
+        Contents::Text(text.to_owned())
+    }
+
+    fn paste_now(clipboard: &mut FakeClipboard) -> bool {
+        clipboard.send_paste();
+        true
+    }
+
+    #[test]
+    fn retried_while_held() {
+        let mut clipboard = FakeClipboard::new(text("copied"), RETRIES);
+        let read = retry(&mut clipboard, "reading", |x| x.read());
+        assert_eq!(read.unwrap(), text("copied"));
+        // the holder is logged on each failure
+        assert_eq!(clipboard.holder_asked.get(), RETRIES);
+
+        let mut clipboard = FakeClipboard::new(text("copied"), RETRIES + 1);
+        assert!(retry(&mut clipboard, "reading", |x| x.read()).is_err());
+        assert_eq!(clipboard.holder_asked.get(), RETRIES + 1);
+    }
+
+    #[test]
+    fn text_restored() {
+        let mut clipboard = FakeClipboard::new(text("copied"), 0);
+        assert!(paste_restoring(&mut clipboard, "pasted", paste_now));
+        assert_eq!(clipboard.pasted, Some(text("pasted")));
+        assert_eq!(clipboard.contents, text("copied"));
+    }
+
+    #[test]
+    fn empty_restored() {
+        let mut clipboard = FakeClipboard::new(Contents::Empty, 0);
+        assert!(paste_restoring(&mut clipboard, "pasted", paste_now));
+        assert_eq!(clipboard.pasted, Some(text("pasted")));
+        assert_eq!(clipboard.writes.last(), Some(&None));
+    }
+
+    #[test]
+    fn other_not_cleared() {
+        let mut clipboard = FakeClipboard::new(Contents::Other, 0);
+        assert!(paste_restoring(&mut clipboard, "pasted", paste_now));
+        assert_eq!(clipboard.writes, [Some("pasted".to_owned())]);
+        assert_eq!(clipboard.contents, text("pasted"));
+    }
+
+    #[test]
+    fn restored_when_given_up() {
+        let mut clipboard = FakeClipboard::new(text("copied"), 0);
+        assert!(!paste_restoring(&mut clipboard, "pasted", |_| false));
+        assert_eq!(clipboard.pasted, None);
+        assert_eq!(clipboard.contents, text("copied"));
+    }
+
+    #[test]
+    fn not_pasted_while_held() {
+        let mut clipboard = FakeClipboard::new(text("copied"), RETRIES + 1);
+        assert!(!paste_restoring(&mut clipboard, "pasted", paste_now));
+        assert_eq!(clipboard.pasted, None);
+        assert!(clipboard.writes.is_empty());
+    }
+}
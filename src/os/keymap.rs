@@ -0,0 +1,300 @@
+//! Translating characters to key strokes on the active keyboard layout.
+//!
+//! Some games read only real key events, so a character is sent as the scan codes of the keys
+//! typing it on the layout the target application uses. Scan codes are of the physical keys
+//! (PC set 1), and `0xE0` in the high byte marks an extended key. A character on a dead key
+//! is typed with a space after it, and an accented letter as the dead key and the letter.
+
+const SHIFT: u16 = 0x2A;
+const CONTROL: u16 = 0x1D;
+// AltGr is the right Alt, which also holds Ctrl on Windows
+const RIGHT_ALT: u16 = 0xE038;
+const SPACE: u16 = 0x39;
+
+/// A keyboard layout with its keys mapped.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Keymap {
+    Us,
+    Jis,
+    De,
+    Fr,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct KeyStroke {
+    pub scan: u16,
+    pub shift: bool,
+    pub alt_gr: bool,
+}
+
+impl KeyStroke {
+    const fn plain(scan: u16) -> Self {
+        Self {
+            scan,
+            shift: false,
+            alt_gr: false,
+        }
+    }
+
+    /// The scan codes to press in order and release in reverse.
+    pub fn keys(&self) -> Vec<u16> {
+        let mut keys = vec![];
+        if self.shift {
+            keys.push(SHIFT);
+        }
+        if self.alt_gr {
+            keys.extend([CONTROL, RIGHT_ALT]);
+        }
+        keys.push(self.scan);
+        keys
+    }
+}
+
+// the scan codes of a row of keys and the characters typed with them without modifiers,
+// with Shift and with AltGr. a space is a key typing nothing on the level.
+type Row = (&'static [u16], &'static str, &'static str, &'static str);
+
+struct Layout {
+    rows: &'static [Row],
+    dead_keys: &'static [(char, KeyStroke)],
+}
+
+const fn range<const N: usize>(start: u16) -> [u16; N] {
+    let mut keys = [0; N];
+    let mut i = 0;
+    while i < N {
+        keys[i] = start + i as u16;
+        i += 1;
+    }
+    keys
+}
+
+const DIGITS: [u16; 12] = range(0x02);
+const TOP_LETTERS: [u16; 12] = range(0x10);
+const MIDDLE_LETTERS: [u16; 11] = range(0x1E);
+const BOTTOM_LETTERS: [u16; 10] = range(0x2C);
+
+const US: Layout = Layout {
+    rows: &[
+        (&[0x29], "`", "~", " "),
+        (&DIGITS, "1234567890-=", "!@#$%^&*()_+", "            "),
+        (&TOP_LETTERS, "qwertyuiop[]", "QWERTYUIOP{}", "            "),
+        (&[0x2B], "\\", "|", " "),
+        (
+            &MIDDLE_LETTERS,
+            "asdfghjkl;'",
+            "ASDFGHJKL:\"",
+            "           ",
+        ),
+        (&BOTTOM_LETTERS, "zxcvbnm,./", "ZXCVBNM<>?", "          "),
+    ],
+    dead_keys: &[],
+};
+
+const JIS: Layout = Layout {
+    rows: &[
+        (&DIGITS, "1234567890-^", "!\"#$%&'() =~", "            "),
+        // the yen key types a backslash, shown as a yen sign with Japanese fonts
+        (&[0x7D], "\\", "|", " "),
+        (&TOP_LETTERS, "qwertyuiop@[", "QWERTYUIOP`{", "            "),
+        (&MIDDLE_LETTERS, "asdfghjkl;:", "ASDFGHJKL+*", "           "),
+        (&[0x2B], "]", "}", " "),
+        (&BOTTOM_LETTERS, "zxcvbnm,./", "ZXCVBNM<>?", "          "),
+        (&[0x73], "\\", "_", " "),
+    ],
+    dead_keys: &[],
+};
+
+const DE: Layout = Layout {
+    rows: &[
+        (&DIGITS, "1234567890ß ", "!\"§$%&/()=? ", " ²³   {[]}\\ "),
+        (&[0x29], " ", "°", " "),
+        (&TOP_LETTERS, "qwertzuiopü+", "QWERTZUIOPÜ*", "@ €        ~"),
+        (&MIDDLE_LETTERS, "asdfghjklöä", "ASDFGHJKLÖÄ", "           "),
+        (&[0x2B], "#", "'", " "),
+        (&[0x56], "<", ">", "|"),
+        (&BOTTOM_LETTERS, "yxcvbnm,.-", "YXCVBNM;:_", "      µ   "),
+    ],
+    dead_keys: &[
+        ('^', KeyStroke::plain(0x29)),
+        ('´', KeyStroke::plain(0x0D)),
+        (
+            '`',
+            KeyStroke {
+                scan: 0x0D,
+                shift: true,
+                alt_gr: false,
+            },
+        ),
+    ],
+};
+
+const FR: Layout = Layout {
+    rows: &[
+        (&[0x29], "²", " ", " "),
+        (&DIGITS, "&é\"'(-è_çà)=", "1234567890°+", "  #{[| \\^@]}"),
+        (&TOP_LETTERS, "azertyuiop $", "AZERTYUIOP £", "  €        ¤"),
+        (&MIDDLE_LETTERS, "qsdfghjklmù", "QSDFGHJKLM%", "           "),
+        (&[0x2B], "*", "µ", " "),
+        (&[0x56], "<", ">", " "),
+        (&BOTTOM_LETTERS, "wxcvbn,;:!", "WXCVBN?./§", "          "),
+    ],
+    dead_keys: &[
+        ('^', KeyStroke::plain(0x1A)),
+        (
+            '¨',
+            KeyStroke {
+                scan: 0x1A,
+                shift: true,
+                alt_gr: false,
+            },
+        ),
+        (
+            '~',
+            KeyStroke {
+                scan: 0x03,
+                shift: false,
+                alt_gr: true,
+            },
+        ),
+        (
+            '`',
+            KeyStroke {
+                scan: 0x08,
+                shift: false,
+                alt_gr: true,
+            },
+        ),
+    ],
+};
+
+// the dead key, the letters and the letters with the accent
+const COMPOSED: &[(char, &str, &str)] = &[
+    ('^', "aeiouAEIOU", "âêîôûÂÊÎÔÛ"),
+    ('¨', "aeiouyAEIOU", "äëïöüÿÄËÏÖÜ"),
+    ('´', "aeiouAEIOU", "áéíóúÁÉÍÓÚ"),
+    ('`', "aeiouAEIOU", "àèìòùÀÈÌÒÙ"),
+    ('~', "anoANO", "ãñõÃÑÕ"),
+];
+
+impl Keymap {
+    /// The keymap of a Windows keyboard layout identifier, like 0x0407 of `00000407`.
+    pub fn from_layout_id(id: u16) -> Option<Self> {
+        match id {
+            0x0409 => Some(Keymap::Us),
+            0x0411 => Some(Keymap::Jis),
+            0x0407 => Some(Keymap::De),
+            0x040C => Some(Keymap::Fr),
+            _ => None,
+        }
+    }
+
+    fn layout(self) -> &'static Layout {
+        match self {
+            Keymap::Us => &US,
+            Keymap::Jis => &JIS,
+            Keymap::De => &DE,
+            Keymap::Fr => &FR,
+        }
+    }
+
+    fn key(self, c: char) -> Option<KeyStroke> {
+        if c == ' ' {
+            return Some(KeyStroke::plain(SPACE));
+        }
+        for &(keys, plain, shifted, alt_gr) in self.layout().rows {
+            let levels = [
+                (plain, false, false),
+                (shifted, true, false),
+                (alt_gr, false, true),
+            ];
+            for (chars, shift, alt_gr) in levels {
+                if let Some(idx) = chars.chars().position(|x| x == c) {
+                    return Some(KeyStroke {
+                        scan: keys[idx],
+                        shift,
+                        alt_gr,
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    fn dead_key(self, c: char) -> Option<KeyStroke> {
+        let dead_keys = self.layout().dead_keys;
+        dead_keys.iter().find(|x| x.0 == c).map(|x| x.1)
+    }
+
+    /// Returns the key strokes typing the character, or None if the layout cannot type it.
+    pub fn strokes(self, c: char) -> Option<Vec<KeyStroke>> {
+        if let Some(key) = self.key(c) {
+            return Some(vec![key]);
+        }
+        if let Some(dead) = self.dead_key(c) {
+            return Some(vec![dead, KeyStroke::plain(SPACE)]);
+        }
+        COMPOSED.iter().find_map(|&(accent, letters, composed)| {
+            let idx = composed.chars().position(|x| x == c)?;
+            let letter = letters.chars().nth(idx)?;
+            Some(vec![self.dead_key(accent)?, self.key(letter)?])
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(keymap: Keymap, c: char) -> Option<Vec<Vec<u16>>> {
+        let strokes = keymap.strokes(c)?;
+        Some(strokes.iter().map(KeyStroke::keys).collect())
+    }
+
+    #[test]
+    fn us_shifted_symbols() {
+        assert_eq!(keys(Keymap::Us, '@'), Some(vec![vec![SHIFT, 0x03]]));
+        assert_eq!(keys(Keymap::Us, '2'), Some(vec![vec![0x03]]));
+        assert_eq!(keys(Keymap::Us, 'A'), Some(vec![vec![SHIFT, 0x1E]]));
+    }
+
+    #[test]
+    fn jis_symbols_on_own_keys() {
+        // @ and [ are next to P instead of on the digits and the brackets of US
+        assert_eq!(keys(Keymap::Jis, '@'), Some(vec![vec![0x1A]]));
+        assert_eq!(keys(Keymap::Jis, '['), Some(vec![vec![0x1B]]));
+    }
+
+    #[test]
+    fn de_alt_gr() {
+        assert_eq!(
+            keys(Keymap::De, '@'),
+            Some(vec![vec![CONTROL, RIGHT_ALT, 0x10]])
+        );
+        assert_eq!(
+            keys(Keymap::De, '€'),
+            Some(vec![vec![CONTROL, RIGHT_ALT, 0x12]])
+        );
+    }
+
+    #[test]
+    fn fr_digits_shifted() {
+        assert_eq!(keys(Keymap::Fr, '1'), Some(vec![vec![SHIFT, 0x02]]));
+        assert_eq!(keys(Keymap::Fr, '&'), Some(vec![vec![0x02]]));
+    }
+
+    #[test]
+    fn dead_keys() {
+        // an accented letter is the dead key and the letter
+        assert_eq!(keys(Keymap::Fr, 'ê'), Some(vec![vec![0x1A], vec![0x12]]));
+        // the character on a dead key is the dead key and a space
+        assert_eq!(keys(Keymap::De, '^'), Some(vec![vec![0x29], vec![SPACE]]));
+    }
+
+    #[test]
+    fn unmapped() {
+        assert_eq!(keys(Keymap::Us, 'ê'), None);
+        assert_eq!(keys(Keymap::Us, 'あ'), None);
+        assert_eq!(Keymap::from_layout_id(0x0419), None);
+    }
+}
@@ -0,0 +1,156 @@
+// the desktop side of the mock on Linux: the focused text field is read over AT-SPI,
+// the accessibility bus of the desktop
+use super::SurroundingText;
+use std::sync::{Mutex, Once};
+use zbus::MatchRule;
+use zbus::blocking::{Connection, MessageIterator};
+use zbus::message::{Message, Type};
+use zbus::names::OwnedUniqueName;
+use zbus::zvariant::{OwnedObjectPath, OwnedValue};
+
+// the application and the path of an accessible object
+type Accessible = (OwnedUniqueName, OwnedObjectPath);
+
+// the events the applications send only while someone listens to them
+const EVENTS: [&str; 3] = [
+    "object:state-changed:focused",
+    "object:text-changed",
+    "object:text-caret-moved",
+];
+
+// the text of the focused field, read again on the thread following the events
+// so that the frame loop never waits for the bus
+static FIELD: Mutex<Option<SurroundingText>> = Mutex::new(None);
+
+fn connect() -> zbus::Result<(Connection, MessageIterator)> {
+    let address: String = Connection::session()?
+        .call_method(
+            Some("org.a11y.Bus"),
+            "/org/a11y/bus",
+            Some("org.a11y.Bus"),
+            "GetAddress",
+            &(),
+        )?
+        .body()
+        .deserialize()?;
+    let connection = zbus::blocking::connection::Builder::address(address.as_str())?.build()?;
+
+    let rule = MatchRule::builder()
+        .msg_type(Type::Signal)
+        .interface("org.a11y.atspi.Event.Object")?
+        .build();
+    let events = MessageIterator::for_match_rule(rule, &connection, None)?;
+    for event in EVENTS {
+        connection.call_method(
+            Some("org.a11y.atspi.Registry"),
+            "/org/a11y/atspi/registry",
+            Some("org.a11y.atspi.Registry"),
+            "RegisterEvent",
+            &(event),
+        )?;
+    }
+    Ok((connection, events))
+}
+
+fn property(
+    connection: &Connection,
+    (app, path): &Accessible,
+    name: &str,
+) -> zbus::Result<OwnedValue> {
+    connection
+        .call_method(
+            Some(app.as_ref()),
+            path.as_ref(),
+            Some("org.freedesktop.DBus.Properties"),
+            "Get",
+            &("org.a11y.atspi.Text", name),
+        )?
+        .body()
+        .deserialize()
+}
+
+fn text(connection: &Connection, accessible: &Accessible) -> zbus::Result<SurroundingText> {
+    let (app, path) = accessible;
+    let text: String = connection
+        .call_method(
+            Some(app.as_ref()),
+            path.as_ref(),
+            Some("org.a11y.atspi.Text"),
+            "GetText",
+            &(0i32, -1i32),
+        )?
+        .body()
+        .deserialize()?;
+    let caret = i32::try_from(property(connection, accessible, "CaretOffset")?)?;
+    Ok(SurroundingText {
+        text,
+        // -1 if the caret is not in the text
+        caret: usize::try_from(caret).unwrap_or_default(),
+    })
+}
+
+fn accessible(event: &Message) -> Option<Accessible> {
+    let header = event.header();
+    let (app, path) = (header.sender()?, header.path()?);
+    Some((app.to_owned().into(), path.to_owned().into()))
+}
+
+// AT-SPI has no call for the focused object, so it's followed with the focus events
+fn follow_focus(connection: Connection, events: MessageIterator) {
+    let mut focused = None;
+    for event in events {
+        let event = match event {
+            Ok(event) => event,
+            Err(err) => {
+                log::error!("at-spi: event: {err}");
+                continue;
+            }
+        };
+        let Some(accessible) = accessible(&event) else {
+            continue;
+        };
+        let member = event.header().member().map(|x| x.to_string());
+        match member.as_deref() {
+            Some("StateChanged") => {
+                // the body goes on with more details not needed here
+                let Ok((state, gained, _)) =
+                    event.body().deserialize_unchecked::<(String, i32, i32)>()
+                else {
+                    continue;
+                };
+                if state != "focused" {
+                    continue;
+                }
+                if gained != 0 {
+                    focused = Some(accessible);
+                } else if focused.as_ref() == Some(&accessible) {
+                    focused = None;
+                }
+            }
+            Some("TextChanged" | "TextCaretMoved") if focused.as_ref() == Some(&accessible) => {}
+            _ => continue,
+        }
+        // most of the focusable objects are not text fields
+        let field = focused.as_ref().and_then(|x| text(&connection, x).ok());
+        *FIELD.lock().unwrap() = field;
+    }
+}
+
+/// The text and the caret of the focused field, if it has the text interface.
+///
+/// Connects on the first call; the field focused before it is not known until the focus moves.
+pub(super) fn focused_text() -> Option<SurroundingText> {
+    static FOLLOWING: Once = Once::new();
+    FOLLOWING.call_once(|| {
+        let spawned = std::thread::Builder::new()
+            .name("at-spi focus".to_owned())
+            .spawn(|| match connect() {
+                Ok((connection, events)) => follow_focus(connection, events),
+                Err(err) => log::error!("at-spi: {err}"),
+            });
+        if let Err(err) = spawned {
+            log::error!("at-spi: starting focus thread: {err}");
+        }
+    });
+    FIELD.lock().unwrap().clone()
+}
@@ -1,6 +1,11 @@
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use std::{fs, io};
 use log::info;
 use once_cell::sync::Lazy;
+use super::clipboard_paste::{Contents, PasteClipboard, Paster};
+use super::keymap::Keymap;
 
 #[cfg(not(windows))]
 pub fn get_appdata_dir() -> &'static Path {
@@ -11,8 +16,88 @@ pub fn get_appdata_dir() -> &'static Path {
     &VALUE
 }
 
+// the keyboard layout is given as a file with its identifier like `00000407`, read once
+static KEYMAP: Lazy<Option<Keymap>> = Lazy::new(|| {
+    let id = fs::read_to_string(get_appdata_dir().join("mock_keyboard_layout.txt")).ok()?;
+    match u32::from_str_radix(id.trim(), 16) {
+        Ok(id) => Keymap::from_layout_id(id as u16),
+        Err(err) => {
+            log::error!("mock: keyboard layout: {err}");
+            None
+        }
+    }
+});
+
 pub fn enter_char(c: char) {
-    info!("mock: enter_char: {c}");
+    match KEYMAP.and_then(|x| x.strokes(c)) {
+        Some(strokes) => {
+            let keys = strokes.iter().map(|x| x.keys()).collect::<Vec<_>>();
+            info!("mock: enter_char: {c} as {keys:x?}");
+        }
+        None => info!("mock: enter_char: {c}"),
+    }
+}
+
+// the focused field is given as a file to try editing with context, read once;
+// without the file, the field focused on the desktop is read with linux_desktop
+static FOCUSED_TEXT: Lazy<Mutex<Option<super::SurroundingText>>> = Lazy::new(|| {
+    let file = fs::File::open(get_appdata_dir().join("mock_focused_text.json")).ok();
+    let text = file.and_then(|file| match serde_json::from_reader(file) {
+        Ok(text) => Some(text),
+        Err(err) => {
+            log::error!("mock: focused text: {err}");
+            None
+        }
+    });
+    Mutex::new(text)
+});
+
+pub fn focused_text() -> Option<super::SurroundingText> {
+    let text = FOCUSED_TEXT.lock().unwrap().clone();
+    #[cfg(all(target_os = "linux", feature = "linux_desktop", not(test)))]
+    let text = text.or_else(super::linux::focused_text);
+    text
+}
+
+/// Runs `f` with the focused field replaced by `text`.
+#[cfg(test)]
+pub fn with_focused_text<R>(text: Option<super::SurroundingText>, f: impl FnOnce() -> R) -> R {
+    // the tests running in parallel share the field
+    static USING: Mutex<()> = Mutex::new(());
+    let _using = USING.lock().unwrap_or_else(|x| x.into_inner());
+    let previous = std::mem::replace(&mut *FOCUSED_TEXT.lock().unwrap(), text);
+    let result = f();
+    *FOCUSED_TEXT.lock().unwrap() = previous;
+    result
+}
+
+/// A focused field with the caret at the character index, for [`with_focused_text`].
+#[cfg(test)]
+pub fn focused_field(text: &str, caret: usize) -> Option<super::SurroundingText> {
+    Some(super::SurroundingText {
+        text: text.to_owned(),
+        caret,
+    })
+}
+
+// the focused window is given as a file like the focused field;
+// without the file, the window focused on the desktop is asked with linux_desktop
+static FOCUSED_APP: Lazy<Option<super::FocusedApp>> = Lazy::new(|| {
+    let file = fs::File::open(get_appdata_dir().join("mock_focused_app.json")).ok()?;
+    match serde_json::from_reader(file) {
+        Ok(app) => Some(app),
+        Err(err) => {
+            log::error!("mock: focused app: {err}");
+            None
+        }
+    }
+});
+
+pub fn focused_app() -> Option<super::FocusedApp> {
+    let app = FOCUSED_APP.clone();
+    #[cfg(all(target_os = "linux", feature = "linux_desktop", not(test)))]
+    let app = app.or_else(super::x11::focused_app);
+    app
 }
 
 pub fn enter_backspace() {
@@ -24,11 +109,71 @@ pub fn enter_enter() {
 }
 
 pub fn enter_text(text: &str) -> bool {
-    info!("mock: copy: {text}");
+    info!("mock: copy: {text:?}");
     true
 }
 
 pub fn copy_text(copy: &str) -> bool {
-    info!("mock: copy: {copy}");
+    info!("mock: copy: {copy:?}");
     true
 }
+
+// the clipboard is kept in memory, and held by the process named in the file if it exists
+static CLIPBOARD: Mutex<Option<String>> = Mutex::new(None);
+
+struct MockClipboard;
+
+impl MockClipboard {
+    fn open(&self) -> io::Result<()> {
+        match self.lock_holder() {
+            Some(_) => Err(io::Error::new(io::ErrorKind::WouldBlock, "clipboard is held")),
+            None => Ok(()),
+        }
+    }
+}
+
+impl PasteClipboard for MockClipboard {
+    fn read(&mut self) -> io::Result<Contents> {
+        self.open()?;
+        let text = CLIPBOARD.lock().unwrap().clone();
+        Ok(text.map_or(Contents::Empty, Contents::Text))
+    }
+
+    fn write(&mut self, text: Option<&str>) -> io::Result<()> {
+        self.open()?;
+        info!("mock: clipboard: {text:?}");
+        *CLIPBOARD.lock().unwrap() = text.map(str::to_owned);
+        Ok(())
+    }
+
+    fn lock_holder(&self) -> Option<String> {
+        // read once, not on every retry
+        static HOLDER: Lazy<Option<String>> = Lazy::new(|| {
+            fs::read_to_string(get_appdata_dir().join("mock_clipboard_holder.txt")).ok()
+        });
+        HOLDER.clone()
+    }
+
+    fn send_paste(&mut self) {
+        info!("mock: ctrl+v: {:?}", CLIPBOARD.lock().unwrap());
+    }
+
+    fn wait_read(&mut self, _: Duration) -> bool {
+        // the paste above has read it
+        true
+    }
+}
+
+// with linux_desktop, the texts are pasted through the desktop clipboard if there's one;
+// the keys are only logged either way
+static PASTER: Lazy<Paster> = Lazy::new(|| {
+    #[cfg(all(target_os = "linux", feature = "linux_desktop", not(test)))]
+    if let Some(clipboard) = super::x11::X11Clipboard::connect() {
+        return Paster::new(clipboard);
+    }
+    Paster::new(MockClipboard)
+});
+
+pub fn paste_text(text: &str) -> bool {
+    PASTER.paste(text)
+}
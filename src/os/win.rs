@@ -2,9 +2,30 @@ use log::*;
 use once_cell::sync::Lazy;
 use std::ffi::c_void;
 use std::path::{Path, PathBuf};
+use std::io;
 use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use winsafe::{co, HwKbMouse, SendInput, KEYBDINPUT};
+use super::clipboard_paste::{Contents, PasteClipboard, Paster};
+use super::keymap::Keymap;
+
+#[link(name = "user32")]
+unsafe extern "system" {
+    fn GetKeyboardLayout(thread_id: u32) -> *mut c_void;
+    fn CountClipboardFormats() -> i32;
+    fn IsClipboardFormatAvailable(format: u32) -> i32;
+    fn GetClipboardData(format: u32) -> *mut c_void;
+    fn GetOpenClipboardWindow() -> *mut c_void;
+}
+
+#[link(name = "kernel32")]
+unsafe extern "system" {
+    fn GlobalLock(memory: *mut c_void) -> *mut c_void;
+    fn GlobalUnlock(memory: *mut c_void) -> i32;
+    fn GlobalSize(memory: *mut c_void) -> usize;
+}
+
+const CF_UNICODETEXT: u32 = 13;
 
 pub fn get_appdata_dir() -> &'static Path {
     static VALUE: Lazy<PathBuf> = Lazy::new(|| {
@@ -40,8 +61,51 @@ fn send_input<const N: usize>(keys: &[co::VK; N]) {
     }
 }
 
+fn send_scan_codes(keys: &[u16]) {
+    let input = |key: u16, up: bool| {
+        let mut flags = co::KEYEVENTF::SCANCODE;
+        if up {
+            flags = flags | co::KEYEVENTF::KEYUP;
+        }
+        if key & 0xE000 == 0xE000 {
+            flags = flags | co::KEYEVENTF::EXTENDEDKEY;
+        }
+        HwKbMouse::Kb(KEYBDINPUT {
+            wScan: key & 0xFF,
+            dwFlags: flags,
+            ..KEYBDINPUT::default()
+        })
+    };
+    let inputs = keys
+        .iter()
+        .map(|&key| input(key, false))
+        .chain(keys.iter().rev().map(|&key| input(key, true)))
+        .collect::<Vec<_>>();
+
+    if let Err(e) = SendInput(&inputs) {
+        error!("failed to send input: {}", e);
+    }
+}
+
+// the layout of the thread of the focused window, since each thread has its own layout
+fn keymap() -> Option<Keymap> {
+    let hwnd = winsafe::HWND::GetForegroundWindow()?;
+    let (thread_id, _) = hwnd.GetWindowThreadProcessId();
+    let layout = unsafe { GetKeyboardLayout(thread_id) } as usize;
+    // the high word is the layout and the low word is the language
+    let keymap = Keymap::from_layout_id((layout >> 16) as u16);
+    if keymap.is_none() {
+        debug!("no keymap for the keyboard layout {layout:08x}");
+    }
+    keymap
+}
+
 pub fn enter_char(c: char) {
-    if c.is_ascii_digit() || c.is_ascii_lowercase() {
+    if let Some(strokes) = keymap().and_then(|x| x.strokes(c)) {
+        for stroke in strokes {
+            send_scan_codes(&stroke.keys());
+        }
+    } else if c.is_ascii_digit() || c.is_ascii_lowercase() {
         // simple input.
         let key = unsafe { co::VK::from_raw(c as u8 as u16) };
         send_input(&[key]);
@@ -55,6 +119,27 @@ pub fn enter_char(c: char) {
     }
 }
 
+// reading the field with UI Automation is not supported yet
+pub fn focused_text() -> Option<super::SurroundingText> {
+    None
+}
+
+pub fn focused_app() -> Option<super::FocusedApp> {
+    let hwnd = winsafe::HWND::GetForegroundWindow()?;
+    let class = hwnd.GetClassName().unwrap_or_default();
+    let (_, process_id) = hwnd.GetWindowThreadProcessId();
+    let process = process_name(process_id).unwrap_or_default();
+    Some(super::FocusedApp { class, process })
+}
+
+// the file name of the executable
+fn process_name(process_id: u32) -> Option<String> {
+    let access = co::PROCESS::QUERY_LIMITED_INFORMATION;
+    let process = winsafe::HPROCESS::OpenProcess(access, false, process_id).ok()?;
+    let path = process.QueryFullProcessImageName(co::PROCESS_NAME::WIN32).ok()?;
+    Some(Path::new(&path).file_name()?.to_string_lossy().into_owned())
+}
+
 pub fn enter_backspace() {
     send_input(&[co::VK::BACK]);
 }
@@ -64,6 +149,19 @@ pub fn enter_enter() {
 }
 
 pub fn enter_text(text: &str) -> bool {
+    // many applications ignore line feeds sent as unicode, so lines are broken with enter
+    for (i, line) in text.split('\n').enumerate() {
+        if i != 0 {
+            enter_enter();
+        }
+        if !line.is_empty() && !enter_unicode(line) {
+            return false;
+        }
+    }
+    true
+}
+
+fn enter_unicode(text: &str) -> bool {
     if let Err(e) = SendInput(
         &text
             .encode_utf16()
@@ -82,6 +180,88 @@ pub fn enter_text(text: &str) -> bool {
     true
 }
 
+// the clipboard opened with the window of this process
+struct WinClipboard;
+
+fn io_error(err: co::ERROR) -> io::Error {
+    io::Error::from_raw_os_error(err.raw() as i32)
+}
+
+impl PasteClipboard for WinClipboard {
+    fn read(&mut self) -> io::Result<Contents> {
+        let hwnd = get_hwnd();
+        let _clipboard = hwnd.OpenClipboard().map_err(io_error)?;
+        unsafe {
+            if CountClipboardFormats() == 0 {
+                return Ok(Contents::Empty);
+            }
+            if IsClipboardFormatAvailable(CF_UNICODETEXT) == 0 {
+                return Ok(Contents::Other);
+            }
+            let data = GetClipboardData(CF_UNICODETEXT);
+            if data.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+            let chars = GlobalLock(data) as *const u16;
+            if chars.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+            let chars = std::slice::from_raw_parts(chars, GlobalSize(data) / 2);
+            let len = chars.iter().position(|&x| x == 0).unwrap_or(chars.len());
+            let text = String::from_utf16_lossy(&chars[..len]);
+            GlobalUnlock(data);
+            Ok(Contents::Text(text.replace("\r\n", "\n")))
+        }
+    }
+
+    fn write(&mut self, text: Option<&str>) -> io::Result<()> {
+        let hwnd = get_hwnd();
+        let clipboard = hwnd.OpenClipboard().map_err(io_error)?;
+        clipboard.EmptyClipboard().map_err(io_error)?;
+        if let Some(text) = text {
+            set_clipboard_text(&clipboard, text).map_err(io_error)?;
+        }
+        Ok(())
+    }
+
+    fn lock_holder(&self) -> Option<String> {
+        let hwnd = unsafe { GetOpenClipboardWindow() };
+        if hwnd.is_null() {
+            return None;
+        }
+        let (_, process_id) = unsafe { winsafe::HWND::from_ptr(hwnd) }.GetWindowThreadProcessId();
+        process_name(process_id)
+    }
+
+    fn send_paste(&mut self) {
+        send_input(&[co::VK::CONTROL, unsafe { co::VK::from_raw(b'V' as u16) }]);
+    }
+
+    fn wait_read(&mut self, timeout: Duration) -> bool {
+        // the application opens the clipboard to read it and closes it after;
+        // a read quicker than the polling is missed and the timeout is waited out
+        let start = Instant::now();
+        let mut opened = false;
+        while start.elapsed() < timeout {
+            let open = !unsafe { GetOpenClipboardWindow() }.is_null();
+            if opened && !open {
+                return true;
+            }
+            opened |= open;
+            sleep(Duration::from_millis(1));
+        }
+        false
+    }
+}
+
+static PASTER: Lazy<Paster> = Lazy::new(|| Paster::new(WinClipboard));
+
+pub fn paste_text(text: &str) -> bool {
+    // the window owning the clipboard must be of the thread handling the messages
+    get_hwnd();
+    PASTER.paste(text)
+}
+
 fn open_clipboard(hwnd: &winsafe::HWND) -> winsafe::SysResult<winsafe::guard::CloseClipboardGuard> {
     for i in 0..9 {
         match hwnd.OpenClipboard() {
@@ -110,21 +290,26 @@ pub(crate) fn copy_text(copy: &str) -> bool {
         return false;
     }
 
-    let encoded = copy.encode_utf16().chain([0]).collect::<Vec<u16>>();
-    let clipboard_data =
-        unsafe { std::slice::from_raw_parts(encoded.as_ptr() as *const u8, encoded.len() * 2) };
-
-    match clipboard.SetClipboardData(co::CF::UNICODETEXT, clipboard_data) {
-        Ok(_) => {}
-        Err(e) => {
-            error!("error in SetClipboardData: {e:?}");
-            return false;
-        }
+    if let Err(e) = set_clipboard_text(&clipboard, copy) {
+        error!("error in SetClipboardData: {e:?}");
+        return false;
     }
 
     true
 }
 
+fn set_clipboard_text(
+    clipboard: &winsafe::guard::CloseClipboardGuard,
+    text: &str,
+) -> winsafe::SysResult<()> {
+    // text on the clipboard has CRLF line ends
+    let text = text.replace('\n', "\r\n");
+    let encoded = text.encode_utf16().chain([0]).collect::<Vec<u16>>();
+    let clipboard_data =
+        unsafe { std::slice::from_raw_parts(encoded.as_ptr() as *const u8, encoded.len() * 2) };
+    clipboard.SetClipboardData(co::CF::UNICODETEXT, clipboard_data).map(drop)
+}
+
 static CURRENT_HWND: std::sync::atomic::AtomicPtr::<c_void> = std::sync::atomic::AtomicPtr::<c_void>::new(std::ptr::null_mut());
 
 fn get_hwnd() -> winsafe::HWND {
@@ -0,0 +1,446 @@
+// the desktop side of the mock on X11: the clipboard is served from a window of its own and
+// pasted with Ctrl+V faked with XTEST, and the focused window is told by the window manager
+use super::FocusedApp;
+use super::clipboard_paste::{Contents, PasteClipboard};
+use std::error::Error;
+use std::sync::mpsc::{Receiver, Sender, channel};
+use std::sync::{Arc, Condvar, Mutex, Once};
+use std::time::{Duration, Instant};
+use std::{fs, io};
+use x11rb::connection::Connection;
+use x11rb::protocol::Event;
+use x11rb::protocol::xproto::*;
+use x11rb::protocol::xtest::ConnectionExt as _;
+use x11rb::rust_connection::RustConnection;
+use x11rb::wrapper::ConnectionExt as _;
+use x11rb::{COPY_DEPTH_FROM_PARENT, COPY_FROM_PARENT, CURRENT_TIME, NONE};
+
+x11rb::atom_manager! {
+    Atoms: AtomsCookie {
+        CLIPBOARD,
+        TARGETS,
+        UTF8_STRING,
+        INCR,
+        _NET_ACTIVE_WINDOW,
+        _NET_WM_PID,
+        // where the clipboard owners put what is read
+        CLEKEY_SELECTION,
+    }
+}
+
+const KEYSYM_CONTROL_L: u32 = 0xffe3;
+const KEYSYM_V: u32 = 0x76;
+// how long the owner of the clipboard is waited for
+const CONVERT_TIMEOUT: Duration = Duration::from_millis(100);
+
+// the text served while owning the clipboard
+#[derive(Default)]
+struct Served {
+    text: Option<String>,
+    // whether the text was read since it was set
+    read: bool,
+}
+
+pub(super) struct X11Clipboard {
+    connection: Arc<RustConnection>,
+    root: Window,
+    window: Window,
+    atoms: Atoms,
+    // keycodes of Control_L and V
+    paste_keys: Option<(Keycode, Keycode)>,
+    served: Arc<(Mutex<Served>, Condvar)>,
+    // the answers to the conversions asked by read
+    notified: Receiver<SelectionNotifyEvent>,
+}
+
+impl X11Clipboard {
+    /// Connects to the display, or None without X11.
+    pub(super) fn connect() -> Option<X11Clipboard> {
+        match Self::try_connect() {
+            Ok(clipboard) => Some(clipboard),
+            Err(err) => {
+                log::error!("x11: {err}");
+                None
+            }
+        }
+    }
+
+    fn try_connect() -> Result<X11Clipboard, Box<dyn Error>> {
+        let (connection, root, atoms) = connect()?;
+        let connection = Arc::new(connection);
+        let window = connection.generate_id()?;
+        connection.create_window(
+            COPY_DEPTH_FROM_PARENT,
+            window,
+            root,
+            0,
+            0,
+            1,
+            1,
+            0,
+            WindowClass::INPUT_ONLY,
+            COPY_FROM_PARENT,
+            &CreateWindowAux::new(),
+        )?;
+        let paste_keys =
+            keycode(&connection, KEYSYM_CONTROL_L)?.zip(keycode(&connection, KEYSYM_V)?);
+        connection.flush()?;
+
+        let served = Arc::new((Mutex::new(Served::default()), Condvar::new()));
+        let (sender, notified) = channel();
+        let serving = (connection.clone(), served.clone());
+        std::thread::Builder::new()
+            .name("x11 clipboard".to_owned())
+            .spawn(move || serve(&serving.0, atoms, &serving.1, sender))?;
+
+        Ok(X11Clipboard {
+            connection,
+            root,
+            window,
+            atoms,
+            paste_keys,
+            served,
+            notified,
+        })
+    }
+
+    fn owner(&self) -> io::Result<Window> {
+        let owner = self.connection.get_selection_owner(self.atoms.CLIPBOARD);
+        Ok(owner
+            .map_err(io::Error::other)?
+            .reply()
+            .map_err(io::Error::other)?
+            .owner)
+    }
+
+    // asks the owner for the clipboard as the target; None if it can't be
+    fn convert(&self, target: Atom) -> io::Result<Option<GetPropertyReply>> {
+        // answers left from a conversion timed out before
+        while self.notified.try_recv().is_ok() {}
+        let atoms = &self.atoms;
+        self.connection
+            .convert_selection(
+                self.window,
+                atoms.CLIPBOARD,
+                target,
+                atoms.CLEKEY_SELECTION,
+                CURRENT_TIME,
+            )
+            .map_err(io::Error::other)?;
+        self.connection.flush().map_err(io::Error::other)?;
+
+        let deadline = Instant::now() + CONVERT_TIMEOUT;
+        let notified = loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            match self.notified.recv_timeout(timeout) {
+                Ok(notified) if notified.target == target => break notified,
+                Ok(_) => continue,
+                Err(_) => {
+                    let err = "the clipboard owner did not answer";
+                    return Err(io::Error::new(io::ErrorKind::TimedOut, err));
+                }
+            }
+        };
+        if notified.property == NONE {
+            return Ok(None);
+        }
+        let reply = self
+            .connection
+            .get_property(
+                true,
+                self.window,
+                atoms.CLEKEY_SELECTION,
+                AtomEnum::ANY,
+                0,
+                u32::MAX / 4,
+            )
+            .map_err(io::Error::other)?
+            .reply()
+            .map_err(io::Error::other)?;
+        Ok(Some(reply))
+    }
+}
+
+impl PasteClipboard for X11Clipboard {
+    fn read(&mut self) -> io::Result<Contents> {
+        let owner = self.owner()?;
+        if owner == NONE {
+            return Ok(Contents::Empty);
+        }
+        if owner == self.window {
+            let text = self.served.0.lock().unwrap().text.clone();
+            return Ok(text.map_or(Contents::Empty, Contents::Text));
+        }
+
+        let Some(targets) = self.convert(self.atoms.TARGETS)? else {
+            return Ok(Contents::Other);
+        };
+        let utf8 = targets
+            .value32()
+            .is_some_and(|mut x| x.any(|x| x == self.atoms.UTF8_STRING));
+        if !utf8 {
+            return Ok(Contents::Other);
+        }
+        match self.convert(self.atoms.UTF8_STRING)? {
+            // large texts are sent in pieces, which are not read
+            Some(text) if text.type_ != self.atoms.INCR => Ok(Contents::Text(
+                String::from_utf8_lossy(&text.value).into_owned(),
+            )),
+            _ => Ok(Contents::Other),
+        }
+    }
+
+    fn write(&mut self, text: Option<&str>) -> io::Result<()> {
+        let mut served = self.served.0.lock().unwrap();
+        served.text = text.map(str::to_owned);
+        served.read = false;
+        drop(served);
+
+        let owner = if text.is_some() { self.window } else { NONE };
+        if text.is_none() && self.owner()? != self.window {
+            // someone else has taken it
+            return Ok(());
+        }
+        self.connection
+            .set_selection_owner(owner, self.atoms.CLIPBOARD, CURRENT_TIME)
+            .map_err(io::Error::other)?;
+        if self.owner()? != owner {
+            let err = "the clipboard was not taken";
+            return Err(io::Error::new(io::ErrorKind::WouldBlock, err));
+        }
+        Ok(())
+    }
+
+    fn lock_holder(&self) -> Option<String> {
+        // X11 doesn't lock the clipboard; the owner may not answer
+        window_class(&self.connection, self.owner().ok()?)
+    }
+
+    fn send_paste(&mut self) {
+        let Some((control, v)) = self.paste_keys else {
+            log::error!("x11: no keys for Ctrl+V");
+            return;
+        };
+        let keys = [
+            (KEY_PRESS_EVENT, control),
+            (KEY_PRESS_EVENT, v),
+            (KEY_RELEASE_EVENT, v),
+            (KEY_RELEASE_EVENT, control),
+        ];
+        for (type_, key) in keys {
+            let faked =
+                self.connection
+                    .xtest_fake_input(type_, key, CURRENT_TIME, self.root, 0, 0, 0);
+            if let Err(err) = faked {
+                log::error!("x11: faking Ctrl+V: {err}");
+                return;
+            }
+        }
+        if let Err(err) = self.connection.flush() {
+            log::error!("x11: faking Ctrl+V: {err}");
+        }
+    }
+
+    fn wait_read(&mut self, timeout: Duration) -> bool {
+        let (served, read) = &*self.served;
+        let served = served.lock().unwrap();
+        let (_served, waited) = read
+            .wait_timeout_while(served, timeout, |x| !x.read)
+            .unwrap();
+        !waited.timed_out()
+    }
+}
+
+fn connect() -> Result<(RustConnection, Window, Atoms), Box<dyn Error>> {
+    let (connection, screen) = RustConnection::connect(None)?;
+    let root = connection.setup().roots[screen].root;
+    let atoms = Atoms::new(&connection)?.reply()?;
+    Ok((connection, root, atoms))
+}
+
+// the class from WM_CLASS
+fn window_class(connection: &RustConnection, window: Window) -> Option<String> {
+    let reply = connection
+        .get_property(false, window, AtomEnum::WM_CLASS, AtomEnum::STRING, 0, 1024)
+        .ok()?
+        .reply()
+        .ok()?;
+    // the instance name comes first
+    let class = reply.value.split(|x| *x == 0).nth(1)?;
+    Some(String::from_utf8_lossy(class).into_owned())
+}
+
+fn property32(
+    connection: &RustConnection,
+    window: Window,
+    property: Atom,
+    type_: AtomEnum,
+) -> Option<u32> {
+    let reply = connection
+        .get_property(false, window, property, type_, 0, 1)
+        .ok()?
+        .reply()
+        .ok()?;
+    reply.value32()?.next()
+}
+
+fn active_app(connection: &RustConnection, root: Window, atoms: &Atoms) -> Option<FocusedApp> {
+    let window = property32(connection, root, atoms._NET_ACTIVE_WINDOW, AtomEnum::WINDOW)?;
+    if window == NONE {
+        return None;
+    }
+    let class = window_class(connection, window).unwrap_or_default();
+    let process = property32(connection, window, atoms._NET_WM_PID, AtomEnum::CARDINAL)
+        .and_then(|pid| fs::read_link(format!("/proc/{pid}/exe")).ok())
+        .and_then(|path| Some(path.file_name()?.to_string_lossy().into_owned()))
+        .unwrap_or_default();
+    Some(FocusedApp { class, process })
+}
+
+// the focused window, looked up again on a thread of its own when the window manager changes it
+// so that sending text never waits for the server
+static ACTIVE_APP: Mutex<Option<FocusedApp>> = Mutex::new(None);
+
+// follows the active window on a connection of its own, until the connection is lost
+fn follow_active_window() -> Result<(), Box<dyn Error>> {
+    let (connection, root, atoms) = connect()?;
+    connection
+        .change_window_attributes(
+            root,
+            &ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+        )?
+        .check()?;
+    *ACTIVE_APP.lock().unwrap() = active_app(&connection, root, &atoms);
+    loop {
+        if let Event::PropertyNotify(event) = connection.wait_for_event()?
+            && event.atom == atoms._NET_ACTIVE_WINDOW
+        {
+            *ACTIVE_APP.lock().unwrap() = active_app(&connection, root, &atoms);
+        }
+    }
+}
+
+/// The focused window told by the window manager. Wayland doesn't tell it, so only X11
+/// applications are known there, if the compositor tells them.
+///
+/// Starts following the window on the first call; until then, nothing is known.
+pub(super) fn focused_app() -> Option<FocusedApp> {
+    static FOLLOWING: Once = Once::new();
+    FOLLOWING.call_once(|| {
+        let spawned = std::thread::Builder::new()
+            .name("x11 active window".to_owned())
+            .spawn(|| {
+                if let Err(err) = follow_active_window() {
+                    log::error!("x11: active window: {err}");
+                }
+            });
+        if let Err(err) = spawned {
+            log::error!("x11: starting active window thread: {err}");
+        }
+    });
+    ACTIVE_APP.lock().unwrap().clone()
+}
+
+// the first keycode with the keysym without modifiers
+fn keycode(connection: &RustConnection, keysym: u32) -> Result<Option<Keycode>, Box<dyn Error>> {
+    let setup = connection.setup();
+    let count = setup.max_keycode - setup.min_keycode + 1;
+    let mapping = connection
+        .get_keyboard_mapping(setup.min_keycode, count)?
+        .reply()?;
+    let per_keycode = usize::from(mapping.keysyms_per_keycode.max(1));
+    let index = mapping
+        .keysyms
+        .chunks(per_keycode)
+        .position(|x| x[0] == keysym);
+    Ok(index.map(|x| setup.min_keycode + x as u8))
+}
+
+// answers the applications reading the clipboard, until the connection is lost
+fn serve(
+    connection: &RustConnection,
+    atoms: Atoms,
+    served: &(Mutex<Served>, Condvar),
+    notified: Sender<SelectionNotifyEvent>,
+) {
+    loop {
+        let event = match connection.wait_for_event() {
+            Ok(event) => event,
+            Err(err) => {
+                log::error!("x11: {err}");
+                return;
+            }
+        };
+        match event {
+            Event::SelectionRequest(request) => {
+                let property = answer(connection, &atoms, served, &request).unwrap_or_else(|err| {
+                    log::error!("x11: answering the clipboard: {err}");
+                    NONE
+                });
+                let event = SelectionNotifyEvent {
+                    response_type: SELECTION_NOTIFY_EVENT,
+                    sequence: 0,
+                    time: request.time,
+                    requestor: request.requestor,
+                    selection: request.selection,
+                    target: request.target,
+                    property,
+                };
+                let sent =
+                    connection.send_event(false, request.requestor, EventMask::NO_EVENT, event);
+                if let Err(err) = sent.map(drop).and_then(|()| connection.flush()) {
+                    log::error!("x11: answering the clipboard: {err}");
+                }
+            }
+            Event::SelectionClear(_) => served.0.lock().unwrap().text = None,
+            Event::SelectionNotify(event) => {
+                // the reader may have given up
+                notified.send(event).ok();
+            }
+            _ => {}
+        }
+    }
+}
+
+// puts the text on the property of the requestor; NONE if it's not served
+fn answer(
+    connection: &RustConnection,
+    atoms: &Atoms,
+    served: &(Mutex<Served>, Condvar),
+    request: &SelectionRequestEvent,
+) -> Result<Atom, x11rb::errors::ConnectionError> {
+    // old clients leave the property to the owner
+    let property = if request.property == NONE {
+        request.target
+    } else {
+        request.property
+    };
+    let mut served_text = served.0.lock().unwrap();
+    let Some(text) = &served_text.text else {
+        return Ok(NONE);
+    };
+    if request.target == atoms.TARGETS {
+        let targets = [atoms.TARGETS, atoms.UTF8_STRING];
+        connection.change_property32(
+            PropMode::REPLACE,
+            request.requestor,
+            property,
+            AtomEnum::ATOM,
+            &targets,
+        )?;
+    } else if request.target == atoms.UTF8_STRING {
+        let text = text.as_bytes();
+        connection.change_property8(
+            PropMode::REPLACE,
+            request.requestor,
+            property,
+            atoms.UTF8_STRING,
+            text,
+        )?;
+        served_text.read = true;
+        served.1.notify_all();
+    } else {
+        return Ok(NONE);
+    }
+    Ok(property)
+}
@@ -0,0 +1,119 @@
+//! Choosing how text is sent by the focused application.
+//!
+//! Some games ignore unicode key events and others block pasting, so a rule for the window
+//! class or the process name of the focused window overrides the output settings.
+
+use crate::Application;
+use crate::config::{OutputMethod, OutputRule};
+use crate::os::{self, FocusedApp};
+
+impl OutputRule {
+    fn matches(&self, app: &FocusedApp) -> bool {
+        // the extension is in any case on Windows
+        let process = match app.process.len().checked_sub(".exe".len()) {
+            Some(i)
+                if app.process.is_char_boundary(i)
+                    && app.process[i..].eq_ignore_ascii_case(".exe") =>
+            {
+                &app.process[..i]
+            }
+            _ => &app.process,
+        };
+        [app.class.as_str(), app.process.as_str(), process]
+            .iter()
+            .any(|x| !x.is_empty() && x.eq_ignore_ascii_case(&self.app))
+    }
+}
+
+fn enter_key_events(text: &str) -> bool {
+    for c in text.chars() {
+        if c == '\n' {
+            os::enter_enter();
+        } else {
+            os::enter_char(c);
+        }
+    }
+    true
+}
+
+impl Application<'_> {
+    // the method and whether the text is entered, not only copied;
+    // the focused window is asked on each send; Windows answers without waiting and X11 has it
+    // kept up to date on a thread
+    fn output_settings(&self) -> (OutputMethod, bool) {
+        let method = if self.config.restore_clipboard {
+            OutputMethod::Paste
+        } else {
+            OutputMethod::Unicode
+        };
+        let always_enter = self.config.always_enter_paste;
+
+        let Some(app) = os::focused_app() else {
+            return (method, always_enter);
+        };
+        match self.config.output_rules.iter().find(|x| x.matches(&app)) {
+            Some(rule) => {
+                log::debug!("output rule for {app:?}: {rule:?}");
+                (rule.output, rule.always_enter_paste.unwrap_or(always_enter))
+            }
+            None => (method, always_enter),
+        }
+    }
+
+    /// Sends the text to the focused application, entering it if `enter` or if always entered.
+    pub(crate) fn send_text(&self, text: &str, enter: bool) -> bool {
+        let (method, always_enter) = self.output_settings();
+        if !(enter || always_enter) {
+            return os::copy_text(text);
+        }
+        match method {
+            OutputMethod::KeyEvents => enter_key_events(text),
+            OutputMethod::Unicode => os::enter_text(text),
+            OutputMethod::Paste => os::paste_text(text),
+            OutputMethod::CopyOnly => os::copy_text(text),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(app: &str) -> OutputRule {
+        OutputRule {
+            app: app.to_owned(),
+            output: OutputMethod::Paste,
+            always_enter_paste: None,
+        }
+    }
+
+    fn app(class: &str, process: &str) -> FocusedApp {
+        FocusedApp {
+            class: class.to_owned(),
+            process: process.to_owned(),
+        }
+    }
+
+    #[test]
+    fn matches_process_without_exe() {
+        assert!(rule("game").matches(&app("", "game.exe")));
+        assert!(rule("game.exe").matches(&app("", "game.exe")));
+        assert!(rule("game").matches(&app("", "game")));
+        assert!(!rule("game").matches(&app("", "game.bin")));
+    }
+
+    #[test]
+    fn matches_ignoring_case() {
+        assert!(rule("Notepad").matches(&app("NOTEPAD", "")));
+        assert!(rule("GAME").matches(&app("", "Game.exe")));
+        assert!(rule("game").matches(&app("", "GAME.EXE")));
+    }
+
+    #[test]
+    fn empty_never_matches() {
+        assert!(!rule("").matches(&app("", "")));
+        assert!(!rule("").matches(&app("", ".exe")));
+        assert!(!rule("game").matches(&app("", "")));
+        assert!(rule("game").matches(&app("", "game")));
+    }
+}
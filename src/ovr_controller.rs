@@ -1,5 +1,9 @@
+use crate::calibration::{StickCalibration, load_calibrations};
+use crate::config::{OverlayAnchor, StickConfig};
 use crate::{CleKeyConfig, HandInfo, KeyboardStatus, LeftRight, Vec2};
 use gl::types::GLuint;
+use glam::Vec3;
+use std::cell::Cell;
 use std::f32::consts::PI;
 use std::fmt;
 use std::path::Path;
@@ -13,6 +17,9 @@ use mock as ovr;
 #[cfg(feature = "openvr")]
 mod ovr;
 
+mod hand_tracking;
+use hand_tracking::HandTracking;
+
 pub type Result<T> = core::result::Result<T, OVRError>;
 
 trait OvrImpl: Sized {
@@ -22,7 +29,16 @@ trait OvrImpl: Sized {
     fn set_active_action_set(&self, kinds: impl IntoIterator<Item = ActionSetKind>);
     fn plane_handle(&self, plane: OverlayPlane) -> &Self::OverlayPlaneHandle;
     fn stick_pos(&self, hand: LeftRight) -> Vec2;
+    /// The serial number of the controller, or None if not connected.
+    fn controller_serial(&self, hand: LeftRight) -> Option<String>;
     fn trigger_status(&self, hand: LeftRight) -> bool;
+    /// How deep the trigger is pulled, from 0.0 to 1.0.
+    fn trigger_value(&self, hand: LeftRight) -> f32;
+    /// The position of the controller in the space overlays with the anchor are placed in,
+    /// or None if the controller or the anchor is not tracked.
+    fn hand_position(&self, hand: LeftRight, anchor: OverlayAnchor) -> Option<Vec3>;
+    /// Joints of the tracked hand in the hand space, or None if not available.
+    fn hand_joints(&self, hand: LeftRight) -> Option<HandJoints>;
     fn play_haptics(
         &self,
         hand: LeftRight,
@@ -33,6 +49,18 @@ trait OvrImpl: Sized {
     );
     fn button_status(&self, button: ButtonKind) -> bool;
     fn click_started(&self, button: HardKeyButton) -> bool;
+    /// Takes the next laser pointer event on the overlay.
+    fn next_mouse_event(&self, plane: OverlayPlane) -> Option<OverlayMouseEvent>;
+}
+
+/// Positions of the joints used for gestures.
+struct HandJoints {
+    wrist: Vec3,
+    thumb_tip: Vec3,
+    index_proximal: Vec3,
+    index_tip: Vec3,
+    middle_tip: Vec3,
+    pinky_proximal: Vec3,
 }
 
 trait OverlayPlaneHandle {
@@ -43,10 +71,12 @@ trait OverlayPlaneHandle {
 }
 
 pub struct OVRController {
-    main: ovr::OVRController,
+    main: HandTracking<ovr::OVRController>,
+    // stick calibration of the connected controllers, for each hand
+    calibrations: [Cell<StickCalibration>; 2],
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 pub enum OverlayPlane {
     Left,
     Right,
@@ -71,49 +101,38 @@ impl From<LeftRight> for OverlayPlane {
 }
 
 impl OVRController {
-    fn update_hand_status(&self, status: &mut HandInfo, hand: LeftRight, clicking: bool) {
+    pub(crate) fn update_hand_status(
+        &self,
+        status: &mut HandInfo,
+        hand: LeftRight,
+        clicking: bool,
+        config: &StickConfig,
+    ) {
         status.stick = self.stick_pos(hand);
         status.selection_old = status.selection;
 
-        fn compute_angle(vec: Vec2) -> i8 {
-            let mut a: f32 = vec.y.atan2(vec.x);
-            // (-pi, pi]
-            a *= -4.0 / PI;
-            // [-4, 4)
-            a += 2.5;
-            // [-1.5, 6.5)
-            if a < 0.0 {
-                a += 8.0
-            }
-            // [0, 8)
-            a.floor() as i8
-        }
-
-        const LOWER_BOUND: f32 = 0.75 * 0.75;
-        const UPPER_BOUND: f32 = 0.8 * 0.8;
+        let upper_bound = config.dead_zone * config.dead_zone;
+        let lower_bound = (config.dead_zone - config.hysteresis).max(0.0).powi(2);
 
         let len_sqrt = status.stick.length_squared();
         status.selection = if clicking {
             // do not change if clicking
             status.selection
-        } else if len_sqrt >= UPPER_BOUND || len_sqrt >= LOWER_BOUND && status.selection != -1 {
-            compute_angle(status.stick)
+        } else if len_sqrt >= upper_bound || len_sqrt >= lower_bound && status.selection != -1 {
+            compute_angle(status.stick, config.sector_offset)
         } else {
             -1
         };
 
-        if status.selection != status.selection_old {
-            self.play_haptics(hand, 0.0, 0.05, 1.0, 0.5);
-        }
-
         status.clicking_old = status.clicking;
         status.clicking = self.trigger_status(hand);
+        status.pull = self.trigger_value(hand);
     }
 
-    pub fn update_status(&self, status: &mut KeyboardStatus) {
+    pub fn update_status(&self, status: &mut KeyboardStatus, config: &StickConfig) {
         let clicking = status.clicking();
-        self.update_hand_status(&mut status.left, LeftRight::Left, clicking);
-        self.update_hand_status(&mut status.right, LeftRight::Right, clicking);
+        self.update_hand_status(&mut status.left, LeftRight::Left, clicking, config);
+        self.update_hand_status(&mut status.right, LeftRight::Right, clicking, config);
     }
 
     pub fn show_overlay(&self, plane: OverlayPlane) {
@@ -138,6 +157,22 @@ impl OVRController {
     }
 }
 
+/// The sector of the ring the stick points, clockwise from 0 at the top.
+pub(crate) fn compute_angle(vec: Vec2, offset: f32) -> i8 {
+    let vec = Vec2::from_angle(offset.to_radians()).rotate(vec);
+    let mut a: f32 = vec.y.atan2(vec.x);
+    // (-pi, pi]
+    a *= -4.0 / PI;
+    // [-4, 4)
+    a += 2.5;
+    // [-1.5, 6.5)
+    if a < 0.0 {
+        a += 8.0
+    }
+    // [0, 8)
+    a.floor() as i8
+}
+
 macro_rules! trait_wrap {
     ($vis: vis fn $name: ident(&self, $($arg_n: ident: $arg_ty: ty),* $(,)?)$( -> $returns: ty)?; $($tt:tt)*) => {
         $vis fn $name(&self, $($arg_n: $arg_ty),*)$( -> $returns)? {
@@ -153,16 +188,47 @@ macro_rules! trait_wrap {
 impl OVRController {
     // trait wrappers
     pub fn new(resources: &Path) -> Result<OVRController> {
-        Ok(Self {
-            main: ovr::OVRController::new(resources)?,
-        })
+        let controller = Self {
+            main: HandTracking::new(resources)?,
+            calibrations: Default::default(),
+        };
+        controller.reload_calibration();
+        Ok(controller)
+    }
+
+    pub fn load_config(&self, config: &CleKeyConfig) -> Result<()> {
+        self.main.load_config(config)
+    }
+
+    /// The stick position with the calibration of the controller applied.
+    pub fn stick_pos(&self, hand: LeftRight) -> Vec2 {
+        let calibration = self.calibrations[hand as usize].get();
+        calibration.apply(self.main.stick_pos(hand))
+    }
+
+    /// The stick position as reported by the runtime.
+    pub fn raw_stick_pos(&self, hand: LeftRight) -> Vec2 {
+        self.main.stick_pos(hand)
+    }
+
+    pub fn reload_calibration(&self) {
+        let calibrations = load_calibrations();
+        for hand in [LeftRight::Left, LeftRight::Right] {
+            let calibration = self
+                .main
+                .controller_serial(hand)
+                .and_then(|serial| calibrations.get(&serial).copied())
+                .unwrap_or_default();
+            self.calibrations[hand as usize].set(calibration);
+        }
     }
 
     trait_wrap! {
-        pub fn load_config(&self, config: &CleKeyConfig) -> Result<()>;
         pub fn set_active_action_set(&self, kinds: impl IntoIterator<Item = ActionSetKind>);
-        pub fn stick_pos(&self, hand: LeftRight) -> Vec2;
+        pub fn controller_serial(&self, hand: LeftRight) -> Option<String>;
         pub fn trigger_status(&self, hand: LeftRight) -> bool;
+        pub fn trigger_value(&self, hand: LeftRight) -> f32;
+        pub fn hand_position(&self, hand: LeftRight, anchor: OverlayAnchor) -> Option<Vec3>;
         pub fn play_haptics(
             &self,
             hand: LeftRight,
@@ -173,6 +239,7 @@ impl OVRController {
         ) -> ();
         pub fn button_status(&self, button: ButtonKind) -> bool;
         pub fn click_started(&self, button: HardKeyButton) -> bool;
+        pub fn next_mouse_event(&self, plane: OverlayPlane) -> Option<OverlayMouseEvent>;
     }
 }
 
@@ -180,7 +247,7 @@ impl OVRController {
 #[cfg(all(feature = "debug_control", not(feature = "openvr")))]
 impl OVRController {
     pub(crate) fn accept_debug_control(&self, event: winit::event::Event<()>) {
-        self.main.accept_debug_control(event);
+        self.main.inner().accept_debug_control(event);
     }
 }
 
@@ -194,6 +261,21 @@ pub enum ActionSetKind {
     Suspender,
 }
 
+#[derive(Copy, Clone, Debug)]
+pub struct OverlayMouseEvent {
+    pub kind: MouseEventKind,
+    // from (0, 0) at the bottom left to (1, 1) at the top right of the overlay
+    pub position: Vec2,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[allow(dead_code)] // only openvr and debug_control make events
+pub enum MouseEventKind {
+    Move,
+    Down,
+    Up,
+}
+
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
 pub enum ButtonKind {
     //BeginInput,
@@ -0,0 +1,205 @@
+//! Input from tracked hands for users without controllers.
+//!
+//! Wraps another backend and replaces the sticks and triggers with hand gestures
+//! when enabled in the config. Everything else is passed through.
+
+use super::*;
+use crate::config::HandTrackingMode;
+
+// distance between finger tips to start pinching, in meters
+const PINCH_DISTANCE: f32 = 0.015;
+// distance between finger tips to stop pinching, in meters; larger than PINCH_DISTANCE
+// so the pinch does not flicker when the tracked fingers jitter around the threshold
+const UNPINCH_DISTANCE: f32 = 0.025;
+// distance between finger tips to be considered as fully released, in meters
+const RELEASE_DISTANCE: f32 = 0.05;
+// how far the pinch is dragged for the full stick tilt, in meters
+const DRAG_RANGE: f32 = 0.06;
+// how much the index finger is bent from the hand for the full stick tilt, in degrees
+const POINTING_RANGE: f32 = 40.0;
+
+pub(super) struct HandTracking<T: OvrImpl> {
+    inner: T,
+    mode: Cell<HandTrackingMode>,
+    // where the drag started for each hand
+    drag_origins: [Cell<Option<Vec3>>; 2],
+    // whether the trigger pinch is held for each hand
+    pinching: [Cell<bool>; 2],
+}
+
+impl<T: OvrImpl> HandTracking<T> {
+    #[cfg(all(feature = "debug_control", not(feature = "openvr")))]
+    pub(super) fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    fn enabled(&self) -> bool {
+        self.mode.get() != HandTrackingMode::Off
+    }
+
+    // stick direction is the index finger direction relative to the back of the hand
+    fn pointing_stick(joints: &HandJoints, hand: LeftRight) -> Vec2 {
+        let forward = (joints.index_proximal - joints.wrist).normalize_or_zero();
+        // from the little finger to the index finger
+        let side = (joints.index_proximal - joints.pinky_proximal).normalize_or_zero();
+        let (right, up) = match hand {
+            LeftRight::Left => (side, -forward.cross(side)),
+            LeftRight::Right => (-side, forward.cross(side)),
+        };
+        let pointing = (joints.index_tip - joints.index_proximal).normalize_or_zero();
+        let stick = Vec2::new(pointing.dot(right), pointing.dot(up));
+        (stick / POINTING_RANGE.to_radians().sin()).clamp_length_max(1.0)
+    }
+
+    // stick direction is how far the hand moved since the thumb and middle finger are pinched
+    fn drag_stick(&self, joints: &HandJoints, hand: LeftRight) -> Vec2 {
+        let origin = &self.drag_origins[hand as usize];
+        let position = self.inner.hand_position(hand, OverlayAnchor::Hmd);
+        let pinching = pinched(origin.get().is_some(), joints.middle_pinch());
+        let (true, Some(position)) = (pinching, position) else {
+            origin.set(None);
+            return Vec2::ZERO;
+        };
+        let start = origin.get().unwrap_or(position);
+        origin.set(Some(start));
+        ((position - start).truncate() / DRAG_RANGE).clamp_length_max(1.0)
+    }
+
+    // distance of the pinch used as the trigger
+    fn trigger_pinch(&self, hand: LeftRight) -> Option<f32> {
+        let joints = self.inner.hand_joints(hand)?;
+        Some(match self.mode.get() {
+            // the index finger is used for pointing
+            HandTrackingMode::Pointing => joints.middle_pinch(),
+            // the middle finger is used for dragging
+            HandTrackingMode::PinchDrag | HandTrackingMode::Off => joints.index_pinch(),
+        })
+    }
+}
+
+// whether the fingers are pinched, staying pinched until they are apart by UNPINCH_DISTANCE
+fn pinched(was_pinched: bool, distance: f32) -> bool {
+    if was_pinched {
+        distance < UNPINCH_DISTANCE
+    } else {
+        distance < PINCH_DISTANCE
+    }
+}
+
+impl HandJoints {
+    fn index_pinch(&self) -> f32 {
+        self.thumb_tip.distance(self.index_tip)
+    }
+
+    fn middle_pinch(&self) -> f32 {
+        self.thumb_tip.distance(self.middle_tip)
+    }
+}
+
+impl<T: OvrImpl> OvrImpl for HandTracking<T> {
+    type OverlayPlaneHandle = T::OverlayPlaneHandle;
+
+    fn new(resources: &Path) -> Result<Self> {
+        Ok(Self {
+            inner: T::new(resources)?,
+            mode: Cell::new(HandTrackingMode::Off),
+            drag_origins: Default::default(),
+            pinching: Default::default(),
+        })
+    }
+
+    fn load_config(&self, config: &CleKeyConfig) -> Result<()> {
+        self.mode.set(config.hand_tracking);
+        self.inner.load_config(config)
+    }
+
+    fn set_active_action_set(&self, kinds: impl IntoIterator<Item = ActionSetKind>) {
+        self.inner.set_active_action_set(kinds)
+    }
+
+    fn plane_handle(&self, plane: OverlayPlane) -> &Self::OverlayPlaneHandle {
+        self.inner.plane_handle(plane)
+    }
+
+    fn stick_pos(&self, hand: LeftRight) -> Vec2 {
+        let mode = self.mode.get();
+        if mode == HandTrackingMode::Off {
+            return self.inner.stick_pos(hand);
+        }
+        let Some(joints) = self.inner.hand_joints(hand) else {
+            return Vec2::ZERO;
+        };
+        match mode {
+            HandTrackingMode::Pointing => Self::pointing_stick(&joints, hand),
+            HandTrackingMode::PinchDrag => self.drag_stick(&joints, hand),
+            HandTrackingMode::Off => unreachable!(),
+        }
+    }
+
+    fn controller_serial(&self, hand: LeftRight) -> Option<String> {
+        // stick calibration does not apply to hands
+        if self.enabled() {
+            None
+        } else {
+            self.inner.controller_serial(hand)
+        }
+    }
+
+    fn trigger_status(&self, hand: LeftRight) -> bool {
+        if !self.enabled() {
+            return self.inner.trigger_status(hand);
+        }
+        let pinching = &self.pinching[hand as usize];
+        let held = self
+            .trigger_pinch(hand)
+            .is_some_and(|distance| pinched(pinching.get(), distance));
+        pinching.set(held);
+        held
+    }
+
+    fn trigger_value(&self, hand: LeftRight) -> f32 {
+        if !self.enabled() {
+            return self.inner.trigger_value(hand);
+        }
+        self.trigger_pinch(hand).map_or(0.0, |distance| {
+            ((RELEASE_DISTANCE - distance) / (RELEASE_DISTANCE - PINCH_DISTANCE)).clamp(0.0, 1.0)
+        })
+    }
+
+    fn hand_position(&self, hand: LeftRight, anchor: OverlayAnchor) -> Option<Vec3> {
+        self.inner.hand_position(hand, anchor)
+    }
+
+    fn hand_joints(&self, hand: LeftRight) -> Option<HandJoints> {
+        self.inner.hand_joints(hand)
+    }
+
+    fn play_haptics(
+        &self,
+        hand: LeftRight,
+        start_seconds_from_now: f32,
+        duration_seconds: f32,
+        frequency: f32,
+        amplitude: f32,
+    ) {
+        self.inner.play_haptics(
+            hand,
+            start_seconds_from_now,
+            duration_seconds,
+            frequency,
+            amplitude,
+        )
+    }
+
+    fn button_status(&self, button: ButtonKind) -> bool {
+        self.inner.button_status(button)
+    }
+
+    fn click_started(&self, button: HardKeyButton) -> bool {
+        self.inner.click_started(button)
+    }
+
+    fn next_mouse_event(&self, plane: OverlayPlane) -> Option<OverlayMouseEvent> {
+        self.inner.next_mouse_event(plane)
+    }
+}
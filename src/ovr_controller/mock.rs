@@ -1,9 +1,10 @@
 use super::*;
+use crate::config::UIMode;
 use crate::{CleKeyConfig, LeftRight};
 use gl::types::GLuint;
-use glam::Vec2;
+use glam::{Vec2, Vec3};
 use std::cell::UnsafeCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::fmt::{Display, Formatter};
 use std::path::Path;
 
@@ -29,7 +30,39 @@ impl OvrImpl for OVRController {
     }
 
     fn load_config(&self, config: &CleKeyConfig) -> Result<()> {
-        assume_used!(config);
+        let positions = match config.ui_mode {
+            UIMode::TwoRing => [
+                (OverlayPlane::Left, &config.two_ring.left_ring.position),
+                (OverlayPlane::Right, &config.two_ring.right_ring.position),
+                (OverlayPlane::Center, &config.two_ring.completion.position),
+            ]
+            .to_vec(),
+            UIMode::OneRing => [
+                (OverlayPlane::Left, &config.one_ring.ring.position),
+                (OverlayPlane::Center, &config.one_ring.completion.position),
+            ]
+            .to_vec(),
+            UIMode::OneHand => [
+                (OverlayPlane::Left, &config.one_hand.ring.position),
+                (OverlayPlane::Center, &config.one_hand.completion.position),
+            ]
+            .to_vec(),
+            UIMode::Flat => [
+                (OverlayPlane::Left, &config.flat.ring.position),
+                (OverlayPlane::Center, &config.flat.keyboard.position),
+            ]
+            .to_vec(),
+        };
+        // there's no overlay to place; just tell what would be done
+        for (plane, position) in positions {
+            log::info!(
+                "overlay {plane:?} at yaw {}, pitch {}, distance {} from {:?}",
+                position.yaw,
+                position.pitch,
+                position.distance,
+                position.anchor
+            );
+        }
         Ok(())
     }
 
@@ -46,10 +79,42 @@ impl OvrImpl for OVRController {
         self.inner().stick(hand)
     }
 
+    fn controller_serial(&self, hand: LeftRight) -> Option<String> {
+        match hand {
+            LeftRight::Left => Some("mock-left".to_owned()),
+            LeftRight::Right => Some("mock-right".to_owned()),
+        }
+    }
+
     fn trigger_status(&self, hand: LeftRight) -> bool {
         self.inner().trigger(hand)
     }
 
+    fn trigger_value(&self, hand: LeftRight) -> f32 {
+        // keyboard controls have no analog trigger
+        if self.inner().trigger(hand) { 1.0 } else { 0.0 }
+    }
+
+    fn hand_position(&self, hand: LeftRight, anchor: OverlayAnchor) -> Option<Vec3> {
+        // controllers held in front of the body, pointing forward
+        fn position(hand: LeftRight) -> Vec3 {
+            match hand {
+                LeftRight::Left => Vec3::new(-0.15, -0.3, -0.4),
+                LeftRight::Right => Vec3::new(0.15, -0.3, -0.4),
+            }
+        }
+        match anchor {
+            OverlayAnchor::Hmd | OverlayAnchor::World => Some(position(hand)),
+            OverlayAnchor::LeftController => Some(position(hand) - position(LeftRight::Left)),
+            OverlayAnchor::RightController => Some(position(hand) - position(LeftRight::Right)),
+        }
+    }
+
+    fn hand_joints(&self, hand: LeftRight) -> Option<HandJoints> {
+        assume_used!(hand);
+        None
+    }
+
     fn play_haptics(
         &self,
         hand: LeftRight,
@@ -75,6 +140,14 @@ impl OvrImpl for OVRController {
         assume_used!(button);
         false
     }
+
+    fn next_mouse_event(&self, plane: OverlayPlane) -> Option<OverlayMouseEvent> {
+        // the debug window stands for the center overlay
+        if !matches!(plane, OverlayPlane::Center) {
+            return None;
+        }
+        unsafe { &mut *self.inner.get() }.mouse_events.pop_front()
+    }
 }
 
 impl OVRController {
@@ -93,6 +166,10 @@ struct Mocked {
     sticks: HashMap<LeftRight, Vec2>,
     triggers: HashMap<LeftRight, bool>,
     buttons: HashMap<ButtonKind, bool>,
+    mouse_events: VecDeque<OverlayMouseEvent>,
+    // None while the mouse is out of the center field
+    #[cfg(feature = "debug_control")]
+    cursor: Option<Vec2>,
 }
 
 impl Mocked {
@@ -112,6 +189,16 @@ impl Mocked {
         self.buttons.get(&hand).copied().unwrap_or_default()
     }
 
+    // the events are taken only in the flat mode; the older ones are dropped in the others
+    #[cfg(feature = "debug_control")]
+    fn push_mouse_event(&mut self, event: OverlayMouseEvent) {
+        const MAX_MOUSE_EVENTS: usize = 64;
+        if self.mouse_events.len() == MAX_MOUSE_EVENTS {
+            self.mouse_events.pop_front();
+        }
+        self.mouse_events.push_back(event);
+    }
+
     #[cfg(feature = "debug_control")]
     pub(crate) fn accept_debug_control(&mut self, event: winit::event::Event<()>) {
         //info!("key event: {:?}", event);
@@ -236,6 +323,33 @@ impl Mocked {
                     _ => (),
                 };
             }
+            // mouse on the debug window for the laser pointer;
+            // the center field is drawn on the bottom half of the window
+            WindowEvent::CursorMoved { position, .. } => {
+                let half_height = crate::WINDOW_HEIGHT as f32 / 2.0;
+                let y = position.y as f32;
+                self.cursor = (y >= half_height).then(|| {
+                    Vec2::new(
+                        position.x as f32 / crate::WINDOW_WIDTH as f32,
+                        1.0 - (y - half_height) / half_height,
+                    )
+                });
+                if let Some(position) = self.cursor {
+                    self.push_mouse_event(OverlayMouseEvent {
+                        kind: MouseEventKind::Move,
+                        position,
+                    });
+                }
+            }
+            WindowEvent::MouseInput { state, .. } => {
+                let kind = match state {
+                    Pressed => MouseEventKind::Down,
+                    Released => MouseEventKind::Up,
+                };
+                if let Some(position) = self.cursor {
+                    self.push_mouse_event(OverlayMouseEvent { kind, position });
+                }
+            }
             _ => {}
         };
     }
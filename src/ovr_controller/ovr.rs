@@ -1,5 +1,5 @@
 use super::*;
-use crate::config::{OverlayPositionConfig, UIMode};
+use crate::config::{OverlayAnchor, OverlayPositionConfig, UIMode};
 use crate::utils::ToCString;
 use crate::{CleKeyConfig, LeftRight, Vec2};
 use gl::types::GLuint;
@@ -19,10 +19,23 @@ pub(super) struct OVRController {
     // input
     action_input_left_stick: VRActionHandle_t,
     action_input_left_click: VRActionHandle_t,
+    action_input_left_trigger: VRActionHandle_t,
     action_input_left_haptic: VRActionHandle_t,
+    action_input_left_pose: VRActionHandle_t,
+    action_input_left_skeleton: VRActionHandle_t,
     action_input_right_stick: VRActionHandle_t,
     action_input_right_click: VRActionHandle_t,
+    action_input_right_trigger: VRActionHandle_t,
     action_input_right_haptic: VRActionHandle_t,
+    action_input_right_pose: VRActionHandle_t,
+    action_input_right_skeleton: VRActionHandle_t,
+    action_input_backspace: VRActionHandle_t,
+    action_input_space: VRActionHandle_t,
+    action_input_enter: VRActionHandle_t,
+    action_input_next_plane: VRActionHandle_t,
+    action_input_sign_plane: VRActionHandle_t,
+    action_input_start_conversion: VRActionHandle_t,
+    action_input_commit: VRActionHandle_t,
     action_set_input: VRActionSetHandle_t,
 
     // waiting
@@ -97,14 +110,37 @@ impl OvrImpl for OVRController {
             input.get_action_handle(cstr!("/actions/input/in/left_stick"))?;
         let action_input_left_click =
             input.get_action_handle(cstr!("/actions/input/in/left_click"))?;
+        let action_input_left_trigger =
+            input.get_action_handle(cstr!("/actions/input/in/left_trigger"))?;
         let action_input_left_haptic =
             input.get_action_handle(cstr!("/actions/input/out/left_haptic"))?;
+        let action_input_left_pose =
+            input.get_action_handle(cstr!("/actions/input/in/left_pose"))?;
+        let action_input_left_skeleton =
+            input.get_action_handle(cstr!("/actions/input/in/left_skeleton"))?;
         let action_input_right_stick =
             input.get_action_handle(cstr!("/actions/input/in/right_stick"))?;
         let action_input_right_click =
             input.get_action_handle(cstr!("/actions/input/in/right_click"))?;
+        let action_input_right_trigger =
+            input.get_action_handle(cstr!("/actions/input/in/right_trigger"))?;
         let action_input_right_haptic =
             input.get_action_handle(cstr!("/actions/input/out/right_haptic"))?;
+        let action_input_right_pose =
+            input.get_action_handle(cstr!("/actions/input/in/right_pose"))?;
+        let action_input_right_skeleton =
+            input.get_action_handle(cstr!("/actions/input/in/right_skeleton"))?;
+        let action_input_backspace =
+            input.get_action_handle(cstr!("/actions/input/in/backspace"))?;
+        let action_input_space = input.get_action_handle(cstr!("/actions/input/in/space"))?;
+        let action_input_enter = input.get_action_handle(cstr!("/actions/input/in/enter"))?;
+        let action_input_next_plane =
+            input.get_action_handle(cstr!("/actions/input/in/next_plane"))?;
+        let action_input_sign_plane =
+            input.get_action_handle(cstr!("/actions/input/in/sign_plane"))?;
+        let action_input_start_conversion =
+            input.get_action_handle(cstr!("/actions/input/in/start_conversion"))?;
+        let action_input_commit = input.get_action_handle(cstr!("/actions/input/in/commit"))?;
         let action_set_input = input.get_action_handle(cstr!("/actions/input"))?;
 
         let action_waiting_begin_input =
@@ -135,10 +171,29 @@ impl OvrImpl for OVRController {
 
         info!("action_left_stick:          {}", action_input_left_stick);
         info!("action_left_click:          {}", action_input_left_click);
+        info!("action_left_trigger:        {}", action_input_left_trigger);
         info!("action_left_haptic:         {}", action_input_left_haptic);
+        info!("action_left_pose:           {}", action_input_left_pose);
+        info!("action_left_skeleton:       {}", action_input_left_skeleton);
         info!("action_right_stick:         {}", action_input_right_stick);
         info!("action_right_click:         {}", action_input_right_click);
+        info!("action_right_trigger:       {}", action_input_right_trigger);
         info!("action_right_haptic:        {}", action_input_right_haptic);
+        info!("action_right_pose:          {}", action_input_right_pose);
+        info!(
+            "action_right_skeleton:      {}",
+            action_input_right_skeleton
+        );
+        info!("action_backspace:           {}", action_input_backspace);
+        info!("action_space:               {}", action_input_space);
+        info!("action_enter:               {}", action_input_enter);
+        info!("action_next_plane:          {}", action_input_next_plane);
+        info!("action_sign_plane:          {}", action_input_sign_plane);
+        info!(
+            "action_start_conversion:    {}",
+            action_input_start_conversion
+        );
+        info!("action_commit:              {}", action_input_commit);
         info!("action_set_input:           {}", action_set_input);
         info!("action_waiting_begin_input: {}", action_waiting_begin_input);
         info!("action_set_waiting:         {}", action_set_waiting);
@@ -148,10 +203,23 @@ impl OvrImpl for OVRController {
         Ok(Self {
             action_input_left_stick,
             action_input_left_click,
+            action_input_left_trigger,
             action_input_left_haptic,
+            action_input_left_pose,
+            action_input_left_skeleton,
             action_input_right_stick,
             action_input_right_click,
+            action_input_right_trigger,
             action_input_right_haptic,
+            action_input_right_pose,
+            action_input_right_skeleton,
+            action_input_backspace,
+            action_input_space,
+            action_input_enter,
+            action_input_next_plane,
+            action_input_sign_plane,
+            action_input_start_conversion,
+            action_input_commit,
             action_set_input,
             action_waiting_begin_input,
             action_set_waiting,
@@ -163,50 +231,119 @@ impl OvrImpl for OVRController {
     }
 
     fn load_config(&self, config: &CleKeyConfig) -> Result<()> {
-        fn overlay_position_matrix(yaw: f32, pitch: f32, distance: f32) -> openvr::HmdMatrix34_t {
-            let mat = glam::Mat4::from_rotation_y(yaw.to_radians())
+        fn overlay_position_matrix(yaw: f32, pitch: f32, distance: f32) -> glam::Mat4 {
+            glam::Mat4::from_rotation_y(yaw.to_radians())
                 * glam::Mat4::from_rotation_x(pitch.to_radians())
-                * glam::Mat4::from_translation(Vec3::new(0.0, 0.0, -distance));
-
-            let mat = mat.transpose();
-            let cols = mat.to_cols_array_2d();
-            openvr::HmdMatrix34_t {
-                m: [cols[0], cols[1], cols[2]],
-            }
+                * glam::Mat4::from_translation(Vec3::new(0.0, 0.0, -distance))
         }
 
-        fn load(handle: &OwnedInVROverlay, config: &OverlayPositionConfig) -> Result<()> {
+        fn load(
+            c: &OVRController,
+            handle: &OwnedInVROverlay,
+            config: &OverlayPositionConfig,
+        ) -> Result<()> {
             handle.set_overlay_width_in_meters(config.width_radio * config.distance)?;
             handle.set_overlay_alpha(1.0)?;
-            handle.set_overlay_transform_tracked_device_relative(
-                0,
-                &overlay_position_matrix(config.yaw, config.pitch, config.distance),
-            )?;
+            let matrix = overlay_position_matrix(config.yaw, config.pitch, config.distance);
+            match config.anchor {
+                OverlayAnchor::Hmd => {
+                    handle.set_overlay_transform_tracked_device_relative(
+                        openvr::k_unTrackedDeviceIndex_Hmd,
+                        &mat4_to_matrix34(&matrix),
+                    )?;
+                }
+                OverlayAnchor::LeftController | OverlayAnchor::RightController => {
+                    let device = c.controller_device(config.anchor).unwrap_or_else(|| {
+                        // the controller may be connected later; the config is loaded again
+                        // each time the keyboard is opened
+                        info!("controller for {:?} not found, using hmd", config.anchor);
+                        openvr::k_unTrackedDeviceIndex_Hmd
+                    });
+                    handle.set_overlay_transform_tracked_device_relative(
+                        device,
+                        &mat4_to_matrix34(&matrix),
+                    )?;
+                }
+                OverlayAnchor::World => {
+                    let base = c
+                        .anchor_pose(OverlayAnchor::World)
+                        .unwrap_or(glam::Mat4::IDENTITY);
+                    handle.set_overlay_transform_absolute(
+                        openvr::TrackingUniverseOrigin::Standing,
+                        &mat4_to_matrix34(&(base * matrix)),
+                    )?;
+                }
+            }
             Ok(())
         }
 
         match config.ui_mode {
             UIMode::TwoRing => {
                 load(
+                    self,
                     &self.overlay_handles[0],
                     &config.two_ring.left_ring.position,
                 )?;
                 load(
+                    self,
                     &self.overlay_handles[1],
                     &config.two_ring.right_ring.position,
                 )?;
                 load(
+                    self,
                     &self.overlay_handles[2],
                     &config.two_ring.completion.position,
                 )?;
             }
             UIMode::OneRing => {
-                load(&self.overlay_handles[0], &config.one_ring.ring.position)?;
                 load(
+                    self,
+                    &self.overlay_handles[0],
+                    &config.one_ring.ring.position,
+                )?;
+                load(
+                    self,
                     &self.overlay_handles[2],
                     &config.one_ring.completion.position,
                 )?;
             }
+            UIMode::OneHand => {
+                load(
+                    self,
+                    &self.overlay_handles[0],
+                    &config.one_hand.ring.position,
+                )?;
+                load(
+                    self,
+                    &self.overlay_handles[2],
+                    &config.one_hand.completion.position,
+                )?;
+            }
+            UIMode::Flat => {
+                load(self, &self.overlay_handles[0], &config.flat.ring.position)?;
+                load(
+                    self,
+                    &self.overlay_handles[2],
+                    &config.flat.keyboard.position,
+                )?;
+            }
+        }
+
+        // the laser pointer is only used for the flat keyboard
+        let center = &self.overlay_handles[2];
+        if matches!(config.ui_mode, UIMode::Flat) {
+            center.set_overlay_input_method(openvr::overlay::InputMethod::Mouse)?;
+            center.set_overlay_mouse_scale(1.0, 1.0)?;
+            center.set_overlay_flag(
+                openvr::overlay::OverlayFlags::MakeOverlaysInteractiveIfVisible,
+                true,
+            )?;
+        } else {
+            center.set_overlay_input_method(openvr::overlay::InputMethod::None)?;
+            center.set_overlay_flag(
+                openvr::overlay::OverlayFlags::MakeOverlaysInteractiveIfVisible,
+                false,
+            )?;
         }
         Ok(())
     }
@@ -270,6 +407,25 @@ impl OvrImpl for OVRController {
         Vec2::new(data.x, data.y)
     }
 
+    fn controller_serial(&self, hand: LeftRight) -> Option<String> {
+        let role = match hand {
+            LeftRight::Left => openvr::TrackedControllerRole::LeftHand,
+            LeftRight::Right => openvr::TrackedControllerRole::RightHand,
+        };
+        let system = self.context.system().ok()?;
+        let device = system.get_tracked_device_index_for_controller_role(role);
+        if device == openvr::k_unTrackedDeviceIndexInvalid {
+            return None;
+        }
+        let serial = system
+            .get_string_tracked_device_property(
+                device,
+                openvr::TrackedDeviceProperty::SerialNumber_String,
+            )
+            .ok()?;
+        Some(serial.to_string_lossy().into_owned())
+    }
+
     fn trigger_status(&self, hand: LeftRight) -> bool {
         let action = match hand {
             LeftRight::Left => self.action_input_left_click,
@@ -284,6 +440,79 @@ impl OvrImpl for OVRController {
         data.bState
     }
 
+    fn trigger_value(&self, hand: LeftRight) -> f32 {
+        let action = match hand {
+            LeftRight::Left => self.action_input_left_trigger,
+            LeftRight::Right => self.action_input_right_trigger,
+        };
+        let data = self
+            .context
+            .input()
+            .expect("inputs")
+            .get_analog_action_data(action, 0)
+            .expect("get_analog_action_data");
+        data.x
+    }
+
+    fn hand_position(&self, hand: LeftRight, anchor: OverlayAnchor) -> Option<Vec3> {
+        let action = match hand {
+            LeftRight::Left => self.action_input_left_pose,
+            LeftRight::Right => self.action_input_right_pose,
+        };
+        let data = self
+            .context
+            .input()
+            .expect("inputs")
+            .get_pose_action_data_relative_to_now(
+                action,
+                openvr::TrackingUniverseOrigin::Standing,
+                0.0,
+                0,
+            )
+            .ok()?;
+        if !data.bActive || !data.pose.bPoseIsValid {
+            return None;
+        }
+
+        let base = self.anchor_pose(anchor)?;
+        let hand = matrix34_to_mat4(&data.pose.mDeviceToAbsoluteTracking);
+        Some(
+            base.inverse()
+                .transform_point3(hand.transform_point3(Vec3::ZERO)),
+        )
+    }
+
+    fn hand_joints(&self, hand: LeftRight) -> Option<HandJoints> {
+        let action = match hand {
+            LeftRight::Left => self.action_input_left_skeleton,
+            LeftRight::Right => self.action_input_right_skeleton,
+        };
+        let mut bones = [openvr::VRBoneTransform_t::default(); 31];
+        self.context
+            .input()
+            .expect("inputs")
+            .get_skeletal_bone_data(
+                action,
+                openvr::VRSkeletalTransformSpace::Model,
+                openvr::VRSkeletalMotionRange::WithoutController,
+                &mut bones,
+            )
+            .ok()?;
+        // see HandSkeletonBone in openvr.h
+        let joint = |index: usize| {
+            let [x, y, z, _] = bones[index].position.v;
+            Vec3::new(x, y, z)
+        };
+        Some(HandJoints {
+            wrist: joint(1),
+            thumb_tip: joint(5),
+            index_proximal: joint(7),
+            index_tip: joint(10),
+            middle_tip: joint(15),
+            pinky_proximal: joint(22),
+        })
+    }
+
     fn play_haptics(
         &self,
         hand: LeftRight,
@@ -327,6 +556,13 @@ impl OvrImpl for OVRController {
     fn click_started(&self, button: HardKeyButton) -> bool {
         let action = match button {
             HardKeyButton::CloseButton => self.action_waiting_begin_input,
+            HardKeyButton::Backspace => self.action_input_backspace,
+            HardKeyButton::Space => self.action_input_space,
+            HardKeyButton::Enter => self.action_input_enter,
+            HardKeyButton::NextPlane => self.action_input_next_plane,
+            HardKeyButton::SignPlane => self.action_input_sign_plane,
+            HardKeyButton::StartConversion => self.action_input_start_conversion,
+            HardKeyButton::Commit => self.action_input_commit,
         };
         let data = self
             .context
@@ -336,6 +572,95 @@ impl OvrImpl for OVRController {
             .unwrap_or_else(|e| panic!("getting button status {:?}: {:?}", button, e));
         data.bState && data.bChanged
     }
+
+    fn next_mouse_event(&self, plane: OverlayPlane) -> Option<OverlayMouseEvent> {
+        let handle = &self.overlay_handles[plane as usize];
+        while let Some(event) = handle.poll_next_overlay_event() {
+            let kind = match event.eventType {
+                openvr::EVREventType::VREvent_MouseMove => MouseEventKind::Move,
+                openvr::EVREventType::VREvent_MouseButtonDown => MouseEventKind::Down,
+                openvr::EVREventType::VREvent_MouseButtonUp => MouseEventKind::Up,
+                _ => continue,
+            };
+            // SAFETY: mouse events have mouse data
+            let mouse = unsafe { event.data.mouse };
+            return Some(OverlayMouseEvent {
+                kind,
+                position: Vec2::new(mouse.x, mouse.y),
+            });
+        }
+        None
+    }
+}
+
+impl OVRController {
+    // pose of the hmd in the standing space
+    fn hmd_pose(&self) -> Option<glam::Mat4> {
+        self.device_pose(openvr::k_unTrackedDeviceIndex_Hmd)
+    }
+
+    fn device_pose(&self, device: u32) -> Option<glam::Mat4> {
+        let mut poses = vec![openvr::TrackedDevicePose_t::default(); device as usize + 1];
+        self.context
+            .system()
+            .expect("system")
+            .get_device_to_absolute_tracking_pose(
+                openvr::TrackingUniverseOrigin::Standing,
+                0.0,
+                &mut poses,
+            );
+        let pose = &poses[device as usize];
+        if !pose.bPoseIsValid {
+            return None;
+        }
+        Some(matrix34_to_mat4(&pose.mDeviceToAbsoluteTracking))
+    }
+
+    // the tracked device of the controller anchor, or None if not connected
+    fn controller_device(&self, anchor: OverlayAnchor) -> Option<u32> {
+        let role = match anchor {
+            OverlayAnchor::LeftController => openvr::TrackedControllerRole::LeftHand,
+            OverlayAnchor::RightController => openvr::TrackedControllerRole::RightHand,
+            OverlayAnchor::Hmd | OverlayAnchor::World => return None,
+        };
+        let device = self
+            .context
+            .system()
+            .expect("system")
+            .get_tracked_device_index_for_controller_role(role);
+        (device != openvr::k_unTrackedDeviceIndexInvalid).then_some(device)
+    }
+
+    // the standing space pose overlays with the anchor are placed relative to
+    fn anchor_pose(&self, anchor: OverlayAnchor) -> Option<glam::Mat4> {
+        match anchor {
+            OverlayAnchor::Hmd => self.hmd_pose(),
+            OverlayAnchor::LeftController | OverlayAnchor::RightController => {
+                self.device_pose(self.controller_device(anchor)?)
+            }
+            OverlayAnchor::World => {
+                // in front of the current head, keeping the overlay level
+                let hmd = self.hmd_pose()?;
+                let forward = hmd.transform_vector3(Vec3::NEG_Z);
+                Some(
+                    glam::Mat4::from_translation(hmd.transform_point3(Vec3::ZERO))
+                        * glam::Mat4::from_rotation_y((-forward.x).atan2(-forward.z)),
+                )
+            }
+        }
+    }
+}
+
+fn matrix34_to_mat4(matrix: &openvr::HmdMatrix34_t) -> glam::Mat4 {
+    let [r0, r1, r2] = matrix.m;
+    glam::Mat4::from_cols_array_2d(&[r0, r1, r2, [0.0, 0.0, 0.0, 1.0]]).transpose()
+}
+
+fn mat4_to_matrix34(matrix: &glam::Mat4) -> openvr::HmdMatrix34_t {
+    let rows = matrix.transpose().to_cols_array_2d();
+    openvr::HmdMatrix34_t {
+        m: [rows[0], rows[1], rows[2]],
+    }
 }
 
 impl OverlayPlaneHandle for OverlayPlaneHandleWrapper {
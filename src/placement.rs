@@ -0,0 +1,142 @@
+//! Placement mode: grab the ring overlays with the controllers and move them.
+//!
+//! While the trigger is held, the ring follows the controller and the stick resizes it.
+//! The completion overlay follows the rings if they share the anchor. Clicking the stick saves
+//! the placement and goes back to settings. A ring anchored to a controller is moved with the
+//! other controller.
+
+use crate::config::{CleKeyConfig, OverlayPositionConfig, UIMode};
+use crate::input_method::HardKeyButton;
+use crate::ovr_controller::{ActionSetKind, OverlayPlane};
+use crate::{Application, ApplicationStatus, LeftRight, show_ring_overlays};
+use glam::Vec3;
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+// ratio of width change per frame at full stick tilt
+const RESIZE_SPEED: f32 = 0.02;
+// moved overlays are sent to the runtime at most this often
+const UPDATE_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Default)]
+pub(crate) struct Placing {
+    // offset from the controller to the center of grabbed ring, for each hand
+    grabbing: [Cell<Option<Vec3>>; 2],
+    // whether the overlays were moved since they were last sent
+    moved: Cell<bool>,
+    last_update: Cell<Option<Instant>>,
+}
+
+impl Placing {
+    fn update_overlays(&self, app: &Application, force: bool) {
+        let due = self
+            .last_update
+            .get()
+            .is_none_or(|x| x.elapsed() >= UPDATE_INTERVAL);
+        if !self.moved.get() || !(due || force) {
+            return;
+        }
+        if let Err(err) = app.ovr_controller.load_config(&app.config) {
+            log::error!("loading config on ovr: {err:?}");
+        }
+        self.moved.set(false);
+        self.last_update.set(Some(Instant::now()));
+    }
+}
+
+// returns the grabbed ring, the completion overlay and how much the completion follows the ring
+fn grabbed_overlays(
+    config: &mut CleKeyConfig,
+    hand: LeftRight,
+) -> (&mut OverlayPositionConfig, &mut OverlayPositionConfig, f32) {
+    match (&config.ui_mode, hand) {
+        (UIMode::TwoRing, LeftRight::Left) => (
+            &mut config.two_ring.left_ring.position,
+            &mut config.two_ring.completion.position,
+            0.5,
+        ),
+        (UIMode::TwoRing, LeftRight::Right) => (
+            &mut config.two_ring.right_ring.position,
+            &mut config.two_ring.completion.position,
+            0.5,
+        ),
+        (UIMode::OneRing, _) => (
+            &mut config.one_ring.ring.position,
+            &mut config.one_ring.completion.position,
+            1.0,
+        ),
+        (UIMode::OneHand, _) => (
+            &mut config.one_hand.ring.position,
+            &mut config.one_hand.completion.position,
+            1.0,
+        ),
+        // the keyboard is grabbed and the candidate ring follows it
+        (UIMode::Flat, _) => (
+            &mut config.flat.keyboard.position,
+            &mut config.flat.ring.position,
+            1.0,
+        ),
+    }
+}
+
+impl ApplicationStatus for Placing {
+    fn tick(&self, app: &mut Application) {
+        app.ovr_controller.set_active_action_set([
+            ActionSetKind::Suspender,
+            ActionSetKind::Input,
+            ActionSetKind::Waiting,
+        ]);
+
+        app.ovr_controller
+            .update_status(&mut app.kbd_status, &app.config.stick);
+
+        show_ring_overlays(app);
+        app.ovr_controller.show_overlay(OverlayPlane::Center);
+
+        let mut released = false;
+        for hand in [LeftRight::Left, LeftRight::Right] {
+            let grabbing = &self.grabbing[hand as usize];
+            let (ring, completion, follow) = grabbed_overlays(&mut app.config, hand);
+            let position = app
+                .ovr_controller
+                .trigger_status(hand)
+                .then(|| app.ovr_controller.hand_position(hand, ring.anchor))
+                .flatten();
+            let Some(position) = position else {
+                released |= grabbing.take().is_some();
+                continue;
+            };
+
+            let offset = match grabbing.get() {
+                Some(offset) => offset,
+                None => {
+                    let offset = ring.point() - position;
+                    grabbing.set(Some(offset));
+                    offset
+                }
+            };
+
+            let (yaw, pitch, distance) = (ring.yaw, ring.pitch, ring.distance);
+            ring.set_point(position + offset);
+            ring.width_radio *= 1.0 + app.ovr_controller.stick_pos(hand).y * RESIZE_SPEED;
+
+            if completion.anchor == ring.anchor {
+                let completion_width = completion.width_radio * completion.distance;
+                completion.yaw += (ring.yaw - yaw) * follow;
+                completion.pitch += (ring.pitch - pitch) * follow;
+                completion.distance += (ring.distance - distance) * follow;
+                completion.width_radio = completion_width / completion.distance;
+            }
+            self.moved.set(true);
+        }
+
+        // the last move is sent on release
+        self.update_overlays(app, released);
+
+        if app.ovr_controller.click_started(HardKeyButton::CloseButton) {
+            app.save_config();
+            app.apply_config();
+            app.open_settings();
+        }
+    }
+}
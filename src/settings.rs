@@ -0,0 +1,300 @@
+//! The settings plane to edit configuration in VR.
+//!
+//! Each direction of the left stick (the ring) is one option and the right stick chooses
+//! how to change it. Changes are applied immediately and saved to the config file when
+//! leaving the settings.
+
+use crate::Application;
+use crate::config::{CleKeyConfig, OverlayPositionConfig, UIMode, list_themes};
+use crate::input_method::{CleKeyButton, CleKeyButtonAction, CleKeyInputTable, InputNextAction};
+
+const CLICK_OFFSET_STEP: u128 = 10;
+const CLICK_LENGTH_STEP: u128 = 25;
+const CLICK_LENGTH_MIN: u128 = 50;
+const DISTANCE_STEP: f32 = 0.05;
+const DISTANCE_RANGE: (f32, f32) = (0.2, 3.0);
+const SCALE_STEP: f32 = 1.1;
+
+pub(crate) static SETTINGS_TABLE: &CleKeyInputTable = &CleKeyInputTable {
+    name: "settings",
+    starts_ime: false,
+    table: [
+        // ui mode
+        builtin_button!("1✋" = ui_one_hand),
+        CleKeyButton::empty(),
+        builtin_button!("One" = ui_one_ring),
+        CleKeyButton::empty(),
+        builtin_button!("Flat" = ui_flat),
+        CleKeyButton::empty(),
+        builtin_button!("Two" = ui_two_ring),
+        CleKeyButton::empty(),
+        // click offset
+        CleKeyButton::empty(),
+        CleKeyButton::empty(),
+        builtin_button!("+" = click_offset_inc),
+        CleKeyButton::empty(),
+        CleKeyButton::empty(),
+        CleKeyButton::empty(),
+        builtin_button!("−" = click_offset_dec),
+        CleKeyButton::empty(),
+        // click length
+        CleKeyButton::empty(),
+        CleKeyButton::empty(),
+        builtin_button!("+" = click_length_inc),
+        CleKeyButton::empty(),
+        CleKeyButton::empty(),
+        CleKeyButton::empty(),
+        builtin_button!("−" = click_length_dec),
+        CleKeyButton::empty(),
+        // always enter paste
+        CleKeyButton::empty(),
+        CleKeyButton::empty(),
+        builtin_button!("ON" = enter_paste_on),
+        CleKeyButton::empty(),
+        CleKeyButton::empty(),
+        CleKeyButton::empty(),
+        builtin_button!("OFF" = enter_paste_off),
+        CleKeyButton::empty(),
+        // always use buffer
+        CleKeyButton::empty(),
+        CleKeyButton::empty(),
+        builtin_button!("ON" = use_buffer_on),
+        CleKeyButton::empty(),
+        CleKeyButton::empty(),
+        CleKeyButton::empty(),
+        builtin_button!("OFF" = use_buffer_off),
+        CleKeyButton::empty(),
+        // overlay distance (left/right) and scale (up/down)
+        builtin_button!("⊕" = scale_inc),
+        CleKeyButton::empty(),
+        builtin_button!("far" = distance_inc),
+        CleKeyButton::empty(),
+        builtin_button!("⊖" = scale_dec),
+        CleKeyButton::empty(),
+        builtin_button!("near" = distance_dec),
+        CleKeyButton::empty(),
+        // theme
+        CleKeyButton::empty(),
+        CleKeyButton::empty(),
+        builtin_button!("▶" = theme_next),
+        CleKeyButton::empty(),
+        CleKeyButton::empty(),
+        CleKeyButton::empty(),
+        builtin_button!("◀" = theme_prev),
+        CleKeyButton::empty(),
+        // placement mode, stick calibration, typing stats and back to the keyboard
+        builtin_button!("Move" = placement),
+        builtin_button!("Back" = back),
+        builtin_button!("Calib" = calibration),
+        builtin_button!("Back" = back),
+        builtin_button!("Stats" = stats),
+        builtin_button!("Back" = back),
+        builtin_button!("Back" = back),
+        builtin_button!("Back" = back),
+    ],
+};
+
+/// Describes the option at the ring position with its current value.
+pub(crate) fn describe(app: &Application, row: i8) -> String {
+    let config = &app.config;
+    fn on_off(value: bool) -> &'static str {
+        if value { "ON" } else { "OFF" }
+    }
+    match row {
+        0 => format!("UI mode: {:?}", config.ui_mode),
+        1 => format!("Click offset: {}ms", config.click.offset),
+        2 => format!("Click length: {}ms", config.click.length),
+        3 => format!("Always paste: {}", on_off(config.always_enter_paste)),
+        4 => format!("Always buffer: {}", on_off(config.always_use_buffer)),
+        5 => {
+            let position = &overlay_positions(config)[0];
+            format!(
+                "Distance: {:.2}m Scale: {:.2}",
+                position.distance, position.width_radio
+            )
+        }
+        6 => format!("Theme: {}", config.theme),
+        7 => "Back (up: move, right: calibrate, down: stats)".to_owned(),
+        _ => "Settings".to_owned(),
+    }
+}
+
+fn overlay_positions(config: &CleKeyConfig) -> Vec<&OverlayPositionConfig> {
+    match config.ui_mode {
+        UIMode::TwoRing => vec![
+            &config.two_ring.left_ring.position,
+            &config.two_ring.right_ring.position,
+            &config.two_ring.completion.position,
+        ],
+        UIMode::OneRing => vec![
+            &config.one_ring.ring.position,
+            &config.one_ring.completion.position,
+        ],
+        UIMode::OneHand => vec![
+            &config.one_hand.ring.position,
+            &config.one_hand.completion.position,
+        ],
+        UIMode::Flat => vec![&config.flat.keyboard.position, &config.flat.ring.position],
+    }
+}
+
+fn overlay_positions_mut(config: &mut CleKeyConfig) -> Vec<&mut OverlayPositionConfig> {
+    match config.ui_mode {
+        UIMode::TwoRing => vec![
+            &mut config.two_ring.left_ring.position,
+            &mut config.two_ring.right_ring.position,
+            &mut config.two_ring.completion.position,
+        ],
+        UIMode::OneRing => vec![
+            &mut config.one_ring.ring.position,
+            &mut config.one_ring.completion.position,
+        ],
+        UIMode::OneHand => vec![
+            &mut config.one_hand.ring.position,
+            &mut config.one_hand.completion.position,
+        ],
+        UIMode::Flat => vec![
+            &mut config.flat.keyboard.position,
+            &mut config.flat.ring.position,
+        ],
+    }
+}
+
+fn update(mgr: &mut Application, f: impl FnOnce(&mut CleKeyConfig)) {
+    f(&mut mgr.config);
+    mgr.apply_config();
+    mgr.set_settings_renderers();
+}
+
+fn ui_one_ring(mgr: &mut Application) {
+    update(mgr, |c| c.ui_mode = UIMode::OneRing);
+}
+
+fn ui_two_ring(mgr: &mut Application) {
+    update(mgr, |c| c.ui_mode = UIMode::TwoRing);
+}
+
+fn ui_flat(mgr: &mut Application) {
+    update(mgr, |c| c.ui_mode = UIMode::Flat);
+}
+
+fn ui_one_hand(mgr: &mut Application) {
+    update(mgr, |c| c.ui_mode = UIMode::OneHand);
+}
+
+fn click_offset_inc(mgr: &mut Application) {
+    update(mgr, |c| c.click.offset += CLICK_OFFSET_STEP);
+}
+
+fn click_offset_dec(mgr: &mut Application) {
+    update(mgr, |c| {
+        c.click.offset = c.click.offset.saturating_sub(CLICK_OFFSET_STEP)
+    });
+}
+
+fn click_length_inc(mgr: &mut Application) {
+    update(mgr, |c| c.click.length += CLICK_LENGTH_STEP);
+}
+
+fn click_length_dec(mgr: &mut Application) {
+    update(mgr, |c| {
+        c.click.length = c
+            .click
+            .length
+            .saturating_sub(CLICK_LENGTH_STEP)
+            .max(CLICK_LENGTH_MIN)
+    });
+}
+
+fn enter_paste_on(mgr: &mut Application) {
+    update(mgr, |c| c.always_enter_paste = true);
+}
+
+fn enter_paste_off(mgr: &mut Application) {
+    update(mgr, |c| c.always_enter_paste = false);
+}
+
+fn use_buffer_on(mgr: &mut Application) {
+    update(mgr, |c| c.always_use_buffer = true);
+}
+
+fn use_buffer_off(mgr: &mut Application) {
+    update(mgr, |c| c.always_use_buffer = false);
+}
+
+fn distance_inc(mgr: &mut Application) {
+    update(mgr, |c| {
+        for position in overlay_positions_mut(c) {
+            position.distance =
+                (position.distance + DISTANCE_STEP).clamp(DISTANCE_RANGE.0, DISTANCE_RANGE.1);
+        }
+    });
+}
+
+fn distance_dec(mgr: &mut Application) {
+    update(mgr, |c| {
+        for position in overlay_positions_mut(c) {
+            position.distance =
+                (position.distance - DISTANCE_STEP).clamp(DISTANCE_RANGE.0, DISTANCE_RANGE.1);
+        }
+    });
+}
+
+fn scale_inc(mgr: &mut Application) {
+    update(mgr, |c| {
+        for position in overlay_positions_mut(c) {
+            position.width_radio *= SCALE_STEP;
+        }
+    });
+}
+
+fn scale_dec(mgr: &mut Application) {
+    update(mgr, |c| {
+        for position in overlay_positions_mut(c) {
+            position.width_radio /= SCALE_STEP;
+        }
+    });
+}
+
+fn theme_next(mgr: &mut Application) {
+    cycle_theme(mgr, 1);
+}
+
+fn theme_prev(mgr: &mut Application) {
+    cycle_theme(mgr, -1);
+}
+
+fn cycle_theme(mgr: &mut Application, step: isize) {
+    let themes = list_themes();
+    if themes.is_empty() {
+        return;
+    }
+    let current = themes.iter().position(|x| *x == mgr.config.theme);
+    let next = match current {
+        Some(index) => (index as isize + step).rem_euclid(themes.len() as isize) as usize,
+        None => 0,
+    };
+    mgr.config.theme = themes[next].clone();
+    mgr.reload_theme();
+    mgr.set_settings_renderers();
+}
+
+// other modes go back to the settings, but the changes are saved in case the app exits there
+fn placement(mgr: &mut Application) {
+    mgr.save_config();
+    mgr.open_placement();
+}
+
+fn calibration(mgr: &mut Application) {
+    mgr.save_config();
+    mgr.open_calibration();
+}
+
+fn stats(mgr: &mut Application) {
+    mgr.save_config();
+    mgr.open_stats();
+}
+
+fn back(mgr: &mut Application) {
+    mgr.close_settings();
+}
@@ -0,0 +1,86 @@
+//! Snippets from the config, expanded from their abbreviation as the last word of the buffer
+//! on sending it, or chosen on the snippet plane.
+//!
+//! `{date}` and `{time}` in a snippet are replaced with the local date and time on input.
+
+use crate::Application;
+use crate::config::Snippet;
+use crate::haptics::HapticEvent;
+use crate::input_method::{CleKeyButton, CleKeyButtonAction, CleKeyInputTable, InputNextAction};
+use crate::table_file::{CELLS, RESERVED_CELLS};
+use std::collections::HashMap;
+use time::OffsetDateTime;
+use time::macros::format_description;
+
+pub(crate) const SNIPPETS_PLANE: &str = "snippets";
+
+/// Replaces the placeholders in the snippet.
+pub(crate) fn fill_placeholders(text: &str) -> String {
+    if !text.contains('{') {
+        return text.to_owned();
+    }
+    // the offset is unavailable on some platforms
+    let now = OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc());
+    let date = now
+        .format(format_description!("[year]-[month]-[day]"))
+        .unwrap_or_default();
+    let time = now
+        .format(format_description!("[hour]:[minute]"))
+        .unwrap_or_default();
+    text.replace("{date}", &date).replace("{time}", &time)
+}
+
+/// Returns the buffer with the abbreviation at its end expanded, if it ends with one.
+pub(crate) fn expand_abbreviation(buffer: &str, snippets: &[Snippet]) -> Option<String> {
+    let start = buffer
+        .rfind(char::is_whitespace)
+        .map_or(0, |x| x + buffer[x..].chars().next().unwrap().len_utf8());
+    let (before, word) = buffer.split_at(start);
+    let snippet = snippets.iter().find(|x| x.abbreviation == word)?;
+    Some(format!("{before}{}", fill_placeholders(&snippet.text)))
+}
+
+#[derive(Default)]
+pub(crate) struct SnippetPlane {
+    // buttons live as long as the app like the tables, so each snippet is leaked once
+    buttons: HashMap<(String, String), CleKeyButton<'static>>,
+}
+
+impl SnippetPlane {
+    fn button(&mut self, snippet: &Snippet) -> CleKeyButton<'static> {
+        let key = (snippet.abbreviation.clone(), snippet.text.clone());
+        *self.buttons.entry(key).or_insert_with(|| {
+            CleKeyButton(Box::leak(Box::new([CleKeyButtonAction {
+                shows: Box::leak(snippet.abbreviation.clone().into_boxed_str()),
+                action: InputNextAction::EnterSnippet(Box::leak(
+                    snippet.text.clone().into_boxed_str(),
+                )),
+            }])))
+        })
+    }
+
+    fn table(&mut self, snippets: &[Snippet]) -> CleKeyInputTable<'static> {
+        let mut table = [CleKeyButton::empty(); CELLS];
+        let cells = (0..CELLS).filter(|x| !RESERVED_CELLS.contains(x));
+        for (cell, snippet) in cells.zip(snippets) {
+            table[cell] = self.button(snippet);
+        }
+        CleKeyInputTable {
+            name: SNIPPETS_PLANE,
+            starts_ime: false,
+            table,
+        }
+    }
+}
+
+impl Application<'_> {
+    pub(crate) fn swap_snippets_plane(&mut self) {
+        self.play_haptic(HapticEvent::PlaneSwitch, None);
+        if self.kbd_status.method.name == SNIPPETS_PLANE {
+            self.set_current_plane();
+        } else {
+            let table = self.snippet_plane.table(&self.config.snippets);
+            self.set_plane(&table);
+        }
+    }
+}
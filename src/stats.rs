@@ -0,0 +1,272 @@
+//! Typing statistics for each session, from opening the keyboard to hiding it or exiting.
+//!
+//! Sessions are appended to appdata as JSON lines to see whether layout changes help.
+//! Time between inputs is counted as typing unless it is an idle gap, and WPM is
+//! computed from that time with five characters as a word.
+
+use crate::global::get_appdata_dir;
+use crate::input_method::HardKeyButton;
+use crate::ovr_controller::{ActionSetKind, OverlayPlane};
+use crate::{Application, ApplicationStatus};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::{fs, io};
+
+// longer pauses are not counted as typing
+const IDLE_GAP: Duration = Duration::from_secs(5);
+const CHARACTERS_PER_WORD: f32 = 5.0;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SessionStats {
+    // unix time in seconds
+    started: u64,
+    // in seconds
+    duration: f32,
+    // characters input with the keys
+    characters: u32,
+    // characters sent to the application
+    committed: u32,
+    backspaces: u32,
+    conversions: u32,
+    // the index of the candidate chosen for each converted word
+    #[serde(rename = "candidateIndices")]
+    candidate_indices: Vec<usize>,
+    // in seconds, without idle gaps
+    #[serde(rename = "typingTime")]
+    typing_time: f32,
+    #[serde(rename = "secondsPerCharacter")]
+    seconds_per_character: f32,
+    wpm: f32,
+}
+
+impl SessionStats {
+    fn new() -> Self {
+        Self {
+            started: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|x| x.as_secs())
+                .unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.characters == 0 && self.backspaces == 0 && self.committed == 0
+    }
+
+    fn update_rates(&mut self) {
+        (self.seconds_per_character, self.wpm) = rates(self.characters, self.typing_time);
+    }
+}
+
+// seconds per character and WPM
+fn rates(characters: u32, typing_time: f32) -> (f32, f32) {
+    if characters == 0 || typing_time <= 0.0 {
+        return (0.0, 0.0);
+    }
+    let characters = characters as f32;
+    (
+        typing_time / characters,
+        characters / CHARACTERS_PER_WORD / (typing_time / 60.0),
+    )
+}
+
+fn get_stats_path() -> PathBuf {
+    get_appdata_dir().join("stats.jsonl")
+}
+
+fn append_session(session: &SessionStats) -> io::Result<()> {
+    fs::create_dir_all(get_stats_path().parent().unwrap())?;
+    let mut writing = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(get_stats_path())?;
+    writeln!(writing, "{}", serde_json::to_string(session)?)?;
+    writing.flush()?;
+    Ok(())
+}
+
+// sums of the saved sessions for the overall WPM
+#[derive(Default)]
+struct Totals {
+    sessions: usize,
+    characters: u32,
+    typing_time: f32,
+}
+
+impl Totals {
+    fn add(&mut self, session: &SessionStats) {
+        self.sessions += 1;
+        self.characters += session.characters;
+        self.typing_time += session.typing_time;
+    }
+}
+
+fn load_totals() -> Totals {
+    let mut totals = Totals::default();
+    load_sessions().iter().for_each(|x| totals.add(x));
+    totals
+}
+
+fn load_sessions() -> Vec<SessionStats> {
+    let file = match File::open(get_stats_path()) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return vec![],
+        Err(err) => {
+            log::error!("loading stats: {err}");
+            return vec![];
+        }
+    };
+    BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| match serde_json::from_str(&line) {
+            Ok(session) => Some(session),
+            Err(err) => {
+                log::warn!("skipping broken stats line: {err}");
+                None
+            }
+        })
+        .collect()
+}
+
+pub(crate) struct Stats {
+    session: SessionStats,
+    started: Instant,
+    last_input: Option<Instant>,
+    // loaded from the file at first use and kept over sessions
+    totals: Option<Totals>,
+    // shown on the center overlay
+    pub(crate) summary: String,
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self {
+            session: SessionStats::new(),
+            started: Instant::now(),
+            last_input: None,
+            totals: None,
+            summary: String::new(),
+        }
+    }
+}
+
+impl Stats {
+    fn typed(&mut self) {
+        let now = Instant::now();
+        if let Some(last) = self.last_input {
+            let gap = now.duration_since(last);
+            if gap < IDLE_GAP {
+                self.session.typing_time += gap.as_secs_f32();
+            }
+        }
+        self.last_input = Some(now);
+    }
+
+    pub(crate) fn character(&mut self) {
+        self.session.characters += 1;
+        self.typed();
+    }
+
+    pub(crate) fn backspace(&mut self) {
+        self.session.backspaces += 1;
+        self.typed();
+    }
+
+    pub(crate) fn conversion(&mut self) {
+        self.session.conversions += 1;
+    }
+
+    pub(crate) fn commit(
+        &mut self,
+        text: &str,
+        candidate_indices: impl IntoIterator<Item = usize>,
+    ) {
+        self.session.committed += text.chars().count() as u32;
+        self.session.candidate_indices.extend(candidate_indices);
+    }
+
+    /// Starts a new session, dropping the current one.
+    pub(crate) fn start_session(&mut self) {
+        let totals = self.totals.take();
+        *self = Self {
+            totals,
+            ..Default::default()
+        };
+    }
+
+    /// Saves the session if anything was typed and starts a new one.
+    pub(crate) fn end_session(&mut self) {
+        let mut session = std::mem::take(&mut self.session);
+        session.duration = self.started.elapsed().as_secs_f32();
+        self.start_session();
+        if session.is_empty() {
+            return;
+        }
+        session.update_rates();
+        if let Err(err) = append_session(&session) {
+            log::error!("saving stats: {err}");
+        }
+        if let Some(totals) = &mut self.totals {
+            totals.add(&session);
+        }
+    }
+
+    fn update_summary(&mut self) {
+        let session = &self.session;
+        let (per_char, wpm) = rates(session.characters, session.typing_time);
+        let candidate = if session.candidate_indices.is_empty() {
+            0.0
+        } else {
+            session.candidate_indices.iter().sum::<usize>() as f32
+                / session.candidate_indices.len() as f32
+        };
+
+        let past = self.totals.get_or_insert_with(load_totals);
+        let characters = past.characters + session.characters;
+        let typing_time = past.typing_time + session.typing_time;
+        let (_, all_wpm) = rates(characters, typing_time);
+
+        self.summary = [
+            format!("Now: {wpm:.1} WPM"),
+            format!("{} chars, {per_char:.2}s/char", session.characters),
+            format!("{} backspaces", session.backspaces),
+            format!("{} conv, cand {candidate:.1}", session.conversions),
+            format!("{} sessions: {all_wpm:.1} WPM", past.sessions + 1),
+        ]
+        .join("\n");
+    }
+}
+
+impl Application<'_> {
+    pub(crate) fn open_stats(&mut self) {
+        self.stats.update_summary();
+        self.set_stats_renderer();
+        self.app_status = Rc::new(ShowingStats);
+    }
+}
+
+struct ShowingStats;
+
+impl ApplicationStatus for ShowingStats {
+    fn tick(&self, app: &mut Application) {
+        app.ovr_controller.set_active_action_set([
+            ActionSetKind::Suspender,
+            ActionSetKind::Input,
+            ActionSetKind::Waiting,
+        ]);
+
+        app.ovr_controller.hide_overlay(OverlayPlane::Left);
+        app.ovr_controller.hide_overlay(OverlayPlane::Right);
+        app.ovr_controller.show_overlay(OverlayPlane::Center);
+
+        if app.ovr_controller.click_started(HardKeyButton::CloseButton) {
+            app.open_settings();
+        }
+    }
+}
@@ -0,0 +1,256 @@
+//! Swipe typing: the left stick is traced through the sectors of the ring while the trigger is
+//! held, and the path is decoded into words of the dictionary of the plane.
+//!
+//! A sector stands for the characters on its row of the table. A word matches the path if the
+//! sectors of its letters appear on the path in order, starting and ending at the same sectors.
+//! Words passing over fewer extra sectors come first, then the order in the dictionary.
+//! The words found are chosen with the conversion candidate ring.
+//!
+//! Only the left stick is traced; the right stick and the right ring are not used for swiping.
+//! Planes without a dictionary, like signs, input with the time selection instead.
+
+use crate::haptics::HapticEvent;
+use crate::input_method::{CleKeyInputTable, InputNextAction};
+use crate::os;
+use crate::ovr_controller::compute_angle;
+use crate::resources::get_resources_dir;
+use crate::{Application, HenkanCandidate, KeyboardStatus};
+use glam::Vec2;
+use std::collections::HashMap;
+use std::fs;
+use std::mem::take;
+
+const MAX_CANDIDATES: usize = 8;
+// stick positions kept for the trail on the ring
+const TRAIL_LENGTH: usize = 90;
+
+#[derive(Default)]
+pub(crate) struct SwipePath {
+    sectors: Vec<i8>,
+    pub(crate) trail: Vec<Vec2>,
+}
+
+#[derive(Default)]
+pub(crate) struct Swipe {
+    // while the trigger is held
+    pub(crate) path: Option<SwipePath>,
+    // by plane name; None if the plane has no dictionary
+    dictionaries: HashMap<String, Option<Dictionary>>,
+}
+
+impl Swipe {
+    // loads the dictionary of the plane at first use
+    fn dictionary(&mut self, plane: &str) -> Option<&Dictionary> {
+        self.dictionaries
+            .entry(plane.to_owned())
+            .or_insert_with(|| Dictionary::load(plane))
+            .as_ref()
+    }
+
+    pub(crate) fn has_dictionary(&mut self, plane: &str) -> bool {
+        self.dictionary(plane).is_some()
+    }
+}
+
+struct Dictionary {
+    // more frequent first
+    words: Vec<String>,
+}
+
+impl Dictionary {
+    fn load(plane: &str) -> Option<Dictionary> {
+        let path = get_resources_dir()
+            .join("swipe")
+            .join(format!("{plane}.txt"));
+        let text = fs::read_to_string(path).ok()?;
+        let words = text
+            .lines()
+            .map(str::trim)
+            .filter(|x| !x.is_empty() && !x.starts_with('#'))
+            .map(str::to_owned)
+            .collect();
+        Some(Dictionary { words })
+    }
+
+    fn decode(&self, table: &CleKeyInputTable, path: &[i8]) -> Vec<String> {
+        let sectors = char_sectors(table);
+        let mut found = self
+            .words
+            .iter()
+            .filter_map(|word| Some((skipped(&word_sectors(word, &sectors)?, path)?, word)))
+            .collect::<Vec<_>>();
+        found.sort_by_key(|&(skipped, _)| skipped);
+        found
+            .into_iter()
+            .take(MAX_CANDIDATES)
+            .map(|(_, word)| word.clone())
+            .collect()
+    }
+}
+
+// the row of the first button inputting each character
+fn char_sectors(table: &CleKeyInputTable) -> HashMap<char, i8> {
+    let mut result = HashMap::new();
+    for (idx, button) in table.table.iter().enumerate() {
+        for action in button.0 {
+            if let InputNextAction::EnterChar(c) = action.action {
+                result.entry(c).or_insert((idx / 8) as i8);
+            }
+        }
+    }
+    result
+}
+
+// repeated sectors are merged since the stick stays there
+fn word_sectors(word: &str, sectors: &HashMap<char, i8>) -> Option<Vec<i8>> {
+    let mut result = Vec::<i8>::new();
+    for c in word.chars() {
+        let sector = *sectors.get(&c)?;
+        if result.last() != Some(&sector) {
+            result.push(sector);
+        }
+    }
+    Some(result)
+}
+
+// how many sectors of the path are passed over, or None if the word does not match
+fn skipped(word: &[i8], path: &[i8]) -> Option<usize> {
+    if word.first() != path.first() || word.last() != path.last() {
+        return None;
+    }
+    let mut rest = word.iter().peekable();
+    for sector in path {
+        if rest.peek() == Some(&sector) {
+            rest.next();
+        }
+    }
+    rest.peek().is_none().then(|| path.len() - word.len())
+}
+
+impl KeyboardStatus {
+    // whether the character before the cursor, or before the caret of the field while the
+    // buffer is empty, is a part of a word
+    fn after_word(&self) -> bool {
+        let before = if self.buffer.is_empty() {
+            os::focused_text().and_then(|field| field.text.chars().take(field.caret).last())
+        } else {
+            self.buffer[..self.cursor].chars().last()
+        };
+        before.is_some_and(char::is_alphanumeric)
+    }
+}
+
+impl Application<'_> {
+    // a click without tracing inputs the first character of the button like a short click
+    pub(crate) fn kbd_swipe_tick(&mut self) -> bool {
+        if self.kbd_status.click_started() {
+            self.swipe.path = Some(SwipePath::default());
+        }
+
+        if self.kbd_status.clicking() {
+            let stick = self.kbd_status.left.stick;
+            if let Some(path) = &mut self.swipe.path {
+                path.trail.push(stick);
+                if path.trail.len() > TRAIL_LENGTH {
+                    path.trail.remove(0);
+                }
+                if stick.length() >= self.config.stick.dead_zone {
+                    let sector = compute_angle(stick, self.config.stick.sector_offset);
+                    if path.sectors.last() != Some(&sector) {
+                        path.sectors.push(sector);
+                    }
+                }
+            }
+        } else if self.kbd_status.click_stopped()
+            && let Some(path) = self.swipe.path.take()
+        {
+            if path.sectors.len() >= 2 {
+                self.input_swiped(&path.sectors);
+            } else if let Some(action) = self
+                .kbd_status
+                .selecting_button()
+                .and_then(|x| x.0.first())
+                .map(|x| &x.action)
+            {
+                self.do_input_action(action);
+            }
+            if take(&mut self.kbd_status.closing) {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn input_swiped(&mut self, path: &[i8]) {
+        let plane = self.kbd_status.method.name;
+        let mut words = self
+            .swipe
+            .dictionary(plane)
+            .map(|x| x.decode(&self.kbd_status.method, path))
+            .unwrap_or_default();
+        if words.is_empty() {
+            self.play_haptic(HapticEvent::ConversionFailure, None);
+            return;
+        }
+        // a word right after one in the buffer or the field is spaced from it
+        if self.kbd_status.after_word() {
+            words.iter_mut().for_each(|x| x.insert(0, ' '));
+        }
+
+        self.record_edit();
+        // the word is inserted at the cursor and the buffer around it is kept as is;
+        // the first word stays on cancel
+        let status = &mut self.kbd_status;
+        let (before, after) = status.buffer.split_at(status.cursor);
+        let fixed = |text: &str| HenkanCandidate {
+            candidates: vec![text.to_owned()],
+            index: 0,
+        };
+        let (before, after) = (fixed(before), fixed(after));
+        status.buffer.insert_str(status.cursor, &words[0]);
+        status.cursor += words[0].len();
+
+        // the word is chosen among the candidates, between the fixed texts around it
+        status.candidates.clear();
+        if !before.candidates[0].is_empty() {
+            status.candidates.push(before);
+        }
+        status.candidates_idx = status.candidates.len();
+        status.candidates.push(HenkanCandidate {
+            candidates: words,
+            index: 0,
+        });
+        if !after.candidates[0].is_empty() {
+            status.candidates.push(after);
+        }
+        status.henkan_using = None;
+        self.set_inputting_table();
+        self.set_henkan_renderers();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::os::{focused_field as field, with_focused_text};
+
+    #[test]
+    fn spaced_after_word_in_field() {
+        let status = KeyboardStatus::new();
+        assert!(with_focused_text(field("hello", 5), || status.after_word()));
+        assert!(with_focused_text(field("hello world", 5), || status.after_word()));
+        assert!(!with_focused_text(field("hello ", 6), || status.after_word()));
+        assert!(!with_focused_text(field("hello", 0), || status.after_word()));
+        assert!(!with_focused_text(None, || status.after_word()));
+    }
+
+    #[test]
+    fn spaced_after_word_in_buffer() {
+        let mut status = KeyboardStatus::new();
+        status.insert_str("ab ");
+        // the buffer comes before the field
+        assert!(!with_focused_text(field("hello", 5), || status.after_word()));
+        status.move_cursor_left();
+        assert!(with_focused_text(None, || status.after_word()));
+    }
+}
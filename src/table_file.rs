@@ -0,0 +1,31 @@
+//! The file format of input tables, shared by the app and the layout optimizer.
+//!
+//! A table file has the 64 cells of a plane, indexed by `left * 8 + right` like the ring.
+//! Each cell lists what the multi-tap chooses in order: a single character, or the name of
+//! a special action prefixed by `#`. A lone `#` is the character. The reserved cells are replaced
+//! by the builtin keys.
+
+use serde::{Deserialize, Serialize};
+
+pub const CELLS: usize = 8 * 8;
+
+/// Cells used for the builtin keys like backspace, space and close.
+pub const RESERVED_CELLS: [usize; 6] = [
+    5 * 8 + 6,
+    5 * 8 + 7,
+    6 * 8 + 6,
+    6 * 8 + 7,
+    7 * 8 + 6,
+    7 * 8 + 7,
+];
+
+pub const ACTION_PREFIX: char = '#';
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TableFile {
+    // the plane replaced with this table
+    pub name: String,
+    #[serde(rename = "startsIme")]
+    pub starts_ime: bool,
+    pub table: Vec<Vec<String>>,
+}
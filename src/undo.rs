@@ -0,0 +1,129 @@
+//! Undo and redo of the edits before the buffer is sent to the application.
+//!
+//! The buffer, the conversion and the plane are saved before each edit, so a wrong dakuten,
+//! a canceled conversion or an accidental plane switch can be reverted.
+//! Sending the buffer clears the history since the text has left the keyboard.
+
+use crate::haptics::HapticEvent;
+use crate::input_method::{CleKeyButton, CleKeyButtonAction, InputNextAction};
+use crate::{Application, HenkanCandidate};
+use std::collections::VecDeque;
+
+const MAX_HISTORY: usize = 100;
+
+const UNDO_ACTION: CleKeyButtonAction = CleKeyButtonAction {
+    shows: "Undo",
+    action: InputNextAction::Intrinsic(Application::undo_key),
+};
+
+const REDO_ACTION: CleKeyButtonAction = CleKeyButtonAction {
+    shows: "Redo",
+    action: InputNextAction::Intrinsic(Application::redo_key),
+};
+
+// the conversion ring chooses only the first entry of a button, so they are separate
+pub(crate) const UNDO_BUTTON: CleKeyButton = CleKeyButton(&[UNDO_ACTION]);
+pub(crate) const REDO_BUTTON: CleKeyButton = CleKeyButton(&[REDO_ACTION]);
+
+// hold enter to undo or redo
+pub(crate) const ENTER_UNDO_BUTTON: CleKeyButton = CleKeyButton(&[
+    CleKeyButtonAction {
+        shows: "⏎",
+        action: InputNextAction::Intrinsic(Application::new_line_key),
+    },
+    UNDO_ACTION,
+    REDO_ACTION,
+]);
+
+struct Snapshot {
+    buffer: String,
+    cursor: usize,
+    candidates: Vec<HenkanCandidate>,
+    candidates_idx: usize,
+    // None for the sign plane
+    plane: Option<&'static str>,
+}
+
+#[derive(Default)]
+pub(crate) struct History {
+    // oldest first
+    undo: VecDeque<Snapshot>,
+    redo: Vec<Snapshot>,
+}
+
+impl History {
+    pub(crate) fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+impl Application<'_> {
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            buffer: self.kbd_status.buffer.clone(),
+            cursor: self.kbd_status.cursor,
+            candidates: self.kbd_status.candidates.clone(),
+            candidates_idx: self.kbd_status.candidates_idx,
+            plane: (!self.is_sign).then(|| self.methods.front().unwrap().name),
+        }
+    }
+
+    /// Saves the current state to undo the edit about to be made.
+    pub(crate) fn record_edit(&mut self) {
+        let snapshot = self.snapshot();
+        self.history.redo.clear();
+        self.history.undo.push_back(snapshot);
+        if self.history.undo.len() > MAX_HISTORY {
+            self.history.undo.pop_front();
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.kbd_status.buffer = snapshot.buffer;
+        self.kbd_status.cursor = snapshot.cursor;
+        self.kbd_status.candidates = snapshot.candidates;
+        self.kbd_status.candidates_idx = snapshot.candidates_idx;
+        self.kbd_status.henkan_using = None;
+
+        // setting the plane also sets the table for the buffer
+        match snapshot.plane {
+            None => {
+                self.is_sign = true;
+                self.set_plane(self.sign_input);
+            }
+            Some(name) => {
+                self.is_sign = false;
+                if let Some(idx) = self.methods.iter().position(|x| x.name == name) {
+                    self.methods.rotate_left(idx);
+                }
+                self.set_plane(self.methods.front().unwrap());
+            }
+        }
+
+        if self.kbd_status.candidates.is_empty() {
+            self.set_default_renderers();
+        } else {
+            self.set_henkan_renderers();
+        }
+    }
+
+    fn undo_key(mgr: &mut Application) {
+        if let Some(snapshot) = mgr.history.undo.pop_back() {
+            mgr.play_haptic(HapticEvent::Backspace, None);
+            let current = mgr.snapshot();
+            mgr.history.redo.push(current);
+            mgr.restore(snapshot);
+        }
+    }
+
+    fn redo_key(mgr: &mut Application) {
+        if let Some(snapshot) = mgr.history.redo.pop() {
+            // the edit is made again
+            mgr.play_haptic(HapticEvent::Character, None);
+            let current = mgr.snapshot();
+            mgr.history.undo.push_back(current);
+            mgr.restore(snapshot);
+        }
+    }
+}
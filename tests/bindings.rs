@@ -0,0 +1,82 @@
+//! Checks the bundled SteamVR bindings against the action manifest.
+
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+fn resources_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("resources")
+}
+
+fn read_json(path: impl AsRef<Path>) -> Value {
+    let path = path.as_ref();
+    let file = File::open(path).unwrap_or_else(|e| panic!("opening {}: {e}", path.display()));
+    serde_json::from_reader(file).unwrap_or_else(|e| panic!("parsing {}: {e}", path.display()))
+}
+
+fn collect_outputs(value: &Value, bound: &mut HashSet<String>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                match (key.as_str(), value) {
+                    ("output", Value::String(output)) => {
+                        bound.insert(output.to_lowercase());
+                    }
+                    _ => collect_outputs(value, bound),
+                }
+            }
+        }
+        Value::Array(values) => values.iter().for_each(|x| collect_outputs(x, bound)),
+        _ => {}
+    }
+}
+
+fn default_binding_urls() -> Vec<String> {
+    let actions = read_json(resources_dir().join("actions.json"));
+    actions["default_bindings"]
+        .as_array()
+        .expect("default_bindings")
+        .iter()
+        .map(|x| x["binding_url"].as_str().expect("binding_url").to_owned())
+        .collect()
+}
+
+#[test]
+fn every_action_is_bound() {
+    let actions = read_json(resources_dir().join("actions.json"));
+    let names = actions["actions"]
+        .as_array()
+        .expect("actions")
+        .iter()
+        .map(|x| x["name"].as_str().expect("action name"))
+        .collect::<Vec<_>>();
+
+    for url in default_binding_urls() {
+        let mut bound = HashSet::new();
+        collect_outputs(
+            &read_json(resources_dir().join(&url))["bindings"],
+            &mut bound,
+        );
+        let missing = names
+            .iter()
+            .filter(|name| !bound.contains(&name.to_lowercase()))
+            .collect::<Vec<_>>();
+        assert!(missing.is_empty(), "{url}: actions not bound: {missing:?}");
+    }
+}
+
+#[test]
+fn every_binding_is_a_default_binding() {
+    let listed = default_binding_urls();
+    for entry in fs::read_dir(resources_dir()).expect("reading resources") {
+        let name = entry.expect("reading resources").file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with("bindings_") && name.ends_with(".json") {
+            assert!(
+                listed.iter().any(|x| *x == name),
+                "{name} is not in default_bindings of actions.json"
+            );
+        }
+    }
+}